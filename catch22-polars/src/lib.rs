@@ -0,0 +1,12 @@
+//! The `catch22` Polars expression plugin. The shared library built from this crate is
+//! registered with `register_plugin_function(function_name="catch22",
+//! is_elementwise=False, returns_scalar=True)`.
+
+use catch22_rs::polars::{catch22_expr, catch22_output_field};
+use polars_core::prelude::*;
+use pyo3_polars::derive::polars_expr;
+
+#[polars_expr(output_type_func = catch22_output_field)]
+fn catch22(inputs: &[Series]) -> PolarsResult<Series> {
+    catch22_expr(inputs)
+}
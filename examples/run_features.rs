@@ -4,9 +4,7 @@ use std::{
     path::PathBuf,
 };
 
-use catch22_rs::features::dn_outlier_include_n_001_mdrmd::{
-    dn_outliner_include_n_001_mdrmd, dn_outliner_include_p_001_mdrmd,
-};
+use catch22_rs::{catch22_all_with, Catch22Options};
 use clap::Parser;
 use itertools::Itertools;
 
@@ -15,9 +13,12 @@ use itertools::Itertools;
 struct Cli {
     #[arg(short, long, value_name = "FILE")]
     input_file: PathBuf,
+    #[arg(long)]
+    catch24: bool,
+    #[arg(long)]
+    no_normalize: bool,
 }
 
-#[allow(clippy::dbg_macro)]
 fn main() {
     let cli = Cli::parse();
 
@@ -30,10 +31,13 @@ fn main() {
         .map(|l| l.unwrap().parse::<f64>().unwrap())
         .collect_vec();
 
-    dbg!(numbers.len());
-
-    let ret_value = dn_outliner_include_p_001_mdrmd(&numbers).unwrap();
-    dbg!(ret_value);
-    let ret_value = dn_outliner_include_n_001_mdrmd(&numbers).unwrap();
-    dbg!(ret_value);
+    let options = Catch22Options {
+        catch24: cli.catch24,
+        normalize: !cli.no_normalize,
+        ..Default::default()
+    };
+    let output = catch22_all_with(&numbers, options).unwrap();
+    for (name, value) in output.names.iter().zip(output.values.iter()) {
+        println!("{name}\t{value}");
+    }
 }
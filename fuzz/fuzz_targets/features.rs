@@ -0,0 +1,66 @@
+#![no_main]
+
+use catch22_rs::catch22::{catch22_features, catch24_extra_features};
+use catch22_rs::context::SeriesContext;
+use catch22_rs::features::co_auto_corr::co_trev_1_num;
+use catch22_rs::features::fc_local_simple::fc_local_simple_mean_residuals;
+use catch22_rs::hist_count::hist_count_flexible;
+use catch22_rs::streaming::{FcLocalSimpleMeanStderr, RecentWindow, StreamingFeature};
+use catch22_rs::utility::sb_coarsegrain;
+use catch22_rs::window::{PartialWindow, WindowExtractor, WindowOptions};
+use catch22_rs::{catch22_all_with, Catch22Options};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let (&flags, data) = match data.split_first() {
+        Some(split) => split,
+        None => return,
+    };
+    // the next eight bytes are the size parameter of the functions that take one
+    let (size, data) = data.split_at(data.len().min(8));
+    let mut bytes = [0; 8];
+    bytes[..size.len()].copy_from_slice(size);
+    let size = u64::from_le_bytes(bytes) as usize;
+    let values = data
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect::<Vec<_>>();
+
+    let context = SeriesContext::new(&values);
+    for feature in catch22_features()
+        .iter()
+        .chain(catch24_extra_features().iter())
+    {
+        let _ = (feature.function)(&context);
+    }
+
+    let options = Catch22Options {
+        catch24: flags & 1 != 0,
+        normalize: flags & 2 != 0,
+        ..Default::default()
+    };
+    let _ = catch22_all_with(&values, options);
+
+    let _ = hist_count_flexible(&values, size);
+    let _ = sb_coarsegrain(&values, size);
+    let _ = fc_local_simple_mean_residuals(&values, size);
+    if let Ok(mut stderr) = FcLocalSimpleMeanStderr::new(size) {
+        stderr.extend(values.iter().copied());
+        let _ = stderr.current();
+    }
+    if let Ok(mut recent) = RecentWindow::new(size) {
+        recent.extend(values.iter().copied());
+        let _ = recent.compute(co_trev_1_num);
+    }
+    let window = WindowOptions {
+        partial: if flags & 4 != 0 {
+            PartialWindow::Shorter
+        } else {
+            PartialWindow::Drop
+        },
+        ..WindowOptions::new(size, size.rotate_left(32))
+    };
+    if let Ok(mut extractor) = WindowExtractor::new(window) {
+        let _ = extractor.extract(&values);
+    }
+});
@@ -0,0 +1,128 @@
+use crate::catch22::{catch22_all_with_planner, extend_row_or_nan, feature_index, Catch22Options};
+use crate::error::Catch22Error;
+use crate::fft::FftPlanner;
+use crate::primitive::Float;
+use itertools::Itertools;
+use ndarray::{Array2, ArrayView1, ArrayView2, Axis};
+
+/// Features of every lane of a matrix, one row per series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureArray<F> {
+    /// Column names.
+    pub names: Vec<&'static str>,
+    pub short_names: Vec<&'static str>,
+    /// `(number of series, names.len())` feature matrix. The rows of failed series are NaN.
+    pub values: Array2<F>,
+    /// The series whose extraction failed, by lane index, in ascending order.
+    pub errors: Vec<(usize, Catch22Error)>,
+}
+
+impl<F: Float> FeatureArray<F> {
+    /// Column of a feature, by long or short name.
+    pub fn column(&self, name: &str) -> Option<ArrayView1<'_, F>> {
+        let index = feature_index(&self.names, &self.short_names, name)?;
+        Some(self.values.column(index))
+    }
+}
+
+/// Computes the catch22 (or catch24) features of every series of `data`, where `axis`
+/// is the time axis: `Axis(1)` treats each row as a series, `Axis(0)` each column.
+///
+/// Series that are not contiguous in memory, such as the columns of a row major matrix,
+/// are copied into a reused buffer instead of being collected one by one. A series that
+/// fails does not abort the extraction; its error is reported in [`FeatureArray::errors`].
+pub fn catch22_axis<F: Float>(
+    data: ArrayView2<F>,
+    axis: Axis,
+    options: Catch22Options,
+) -> FeatureArray<F> {
+    let features = options.features::<F>();
+    let lanes = data.lanes(axis);
+
+    let mut planner = FftPlanner::new();
+    let mut buffer = Vec::with_capacity(data.len_of(axis));
+    let num_lanes = lanes.clone().into_iter().len();
+    let mut values = Vec::with_capacity(num_lanes * features.len());
+    let mut errors = vec![];
+    for (i, lane) in lanes.into_iter().enumerate() {
+        let series = match lane.as_slice() {
+            Some(series) => series,
+            None => {
+                buffer.clear();
+                buffer.extend(lane.iter().copied());
+                &buffer
+            }
+        };
+        extend_row_or_nan(&mut values, features.len(), &mut errors, i, |values| {
+            values.extend(catch22_all_with_planner(series, options, &mut planner)?.values);
+            Ok(())
+        });
+    }
+    let values = Array2::from_shape_vec((num_lanes, features.len()), values)
+        .expect("one row of features per lane");
+
+    FeatureArray {
+        names: features.iter().map(|f| f.name).collect_vec(),
+        short_names: features.iter().map(|f| f.short_name).collect_vec(),
+        values,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catch22::catch22_all;
+    use crate::missing::MissingPolicy;
+    use crate::test_utility::load_test_data;
+
+    #[test]
+    fn test_catch22_axis_rows_and_strided_columns() {
+        let numbers = load_test_data::<f64>();
+        let series = (0..5)
+            .map(|i| numbers[i * 10..i * 10 + 200].to_vec())
+            .collect_vec();
+        let rows = Array2::from_shape_fn((5, 200), |(i, j)| series[i][j]);
+        let columns = Array2::from_shape_fn((200, 5), |(j, i)| series[i][j]);
+        assert!(columns.column(0).as_slice().is_none());
+
+        let by_row = catch22_axis(rows.view(), Axis(1), Default::default());
+        let by_column = catch22_axis(columns.view(), Axis(0), Default::default());
+
+        assert_eq!(by_row, by_column);
+        assert!(by_row.errors.is_empty());
+        assert_eq!(by_row.values.dim(), (5, 22));
+        for (i, s) in series.iter().enumerate() {
+            assert_eq!(
+                by_row.values.row(i).to_vec(),
+                catch22_all(s).unwrap().values
+            );
+        }
+        assert_eq!(
+            by_row.column("dfa").unwrap()[0],
+            by_row.values[[0, by_row.names.len() - 3]]
+        );
+    }
+
+    #[test]
+    fn test_catch22_axis_failing_lane_is_nan() {
+        let numbers = load_test_data::<f64>();
+        let mut data = Array2::from_shape_fn((3, 100), |(i, j)| numbers[i * 50 + j]);
+        data[[1, 10]] = f64::NAN;
+        let options = Catch22Options {
+            missing: MissingPolicy::Error,
+            ..Default::default()
+        };
+
+        let output = catch22_axis(data.view(), Axis(1), options);
+
+        assert_eq!(output.errors, [(1, Catch22Error::MissingValue(10))]);
+        assert!(output.values.row(1).iter().all(|x| x.is_nan()));
+        for i in [0, 2] {
+            assert_eq!(
+                output.values.row(i).to_vec(),
+                catch22_all(&data.row(i).to_vec()).unwrap().values
+            );
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use crate::catch22::{catch22_all_with_planner, Catch22Options, Catch22Output};
+use crate::error::{Catch22Error, Catch22Result};
+use crate::fft::FftPlanner;
+use arrow_array::{Array, ArrayRef, Float64Array, ListArray, StructArray};
+use arrow_buffer::NullBuffer;
+use arrow_schema::{DataType, Field, Fields};
+use itertools::Itertools;
+use std::sync::Arc;
+
+/// Computes the catch22 (or catch24) features of a single series. Null values are
+/// treated as NaN.
+pub fn catch22_float64_array(
+    array: &Float64Array,
+    options: Catch22Options,
+) -> Catch22Result<Catch22Output<f64>> {
+    let mut buffer = vec![];
+    catch22_all_with_planner(
+        values_of(array, &mut buffer),
+        options,
+        &mut FftPlanner::new(),
+    )
+}
+
+/// Computes the catch22 (or catch24) features of every list of `array`, whose values
+/// must be `Float64`.
+///
+/// Returns a struct array with one `Float64` field per feature, named by the long
+/// feature name. Null lists and lists whose extraction fails give null rows; the latter
+/// are returned with their errors, by index into `array`, in ascending order.
+pub fn catch22_list_array(
+    array: &ListArray,
+    options: Catch22Options,
+) -> Catch22Result<(StructArray, Vec<(usize, Catch22Error)>)> {
+    let values = array
+        .values()
+        .as_any()
+        .downcast_ref::<Float64Array>()
+        .ok_or_else(|| Catch22Error::UnsupportedDataType(array.value_type().to_string()))?;
+
+    let features = options.features::<f64>();
+    let mut columns = vec![Vec::with_capacity(array.len()); features.len()];
+    let mut valid = Vec::with_capacity(array.len());
+    let mut errors = vec![];
+
+    let mut planner = FftPlanner::new();
+    let mut buffer = vec![];
+    for (i, (&start, &end)) in array.value_offsets().iter().tuple_windows().enumerate() {
+        let output = if array.is_null(i) {
+            None
+        } else {
+            let series = values.slice(start as usize, (end - start) as usize);
+            catch22_all_with_planner(values_of(&series, &mut buffer), options, &mut planner)
+                .map_err(|e| errors.push((i, e)))
+                .ok()
+        };
+
+        valid.push(output.is_some());
+        for (j, column) in columns.iter_mut().enumerate() {
+            column.push(output.as_ref().map(|o| o.values[j]));
+        }
+    }
+
+    let fields = features
+        .iter()
+        .map(|f| Field::new(f.name, DataType::Float64, true))
+        .collect::<Fields>();
+    let arrays = columns
+        .into_iter()
+        .map(|c| Arc::new(Float64Array::from(c)) as ArrayRef)
+        .collect_vec();
+
+    let output = StructArray::new(fields, arrays, Some(NullBuffer::from(valid)));
+    Ok((output, errors))
+}
+
+fn values_of<'a>(array: &'a Float64Array, buffer: &'a mut Vec<f64>) -> &'a [f64] {
+    if array.null_count() == 0 {
+        return array.values();
+    }
+    buffer.clear();
+    buffer.extend(array.iter().map(|x| x.unwrap_or(f64::NAN)));
+    buffer
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catch22::catch22_all;
+    use crate::test_utility::load_test_data;
+    use arrow_array::types::Float64Type;
+
+    #[test]
+    fn test_catch22_list_array_one_row_per_list() {
+        let numbers = load_test_data::<f64>();
+        let lists = vec![
+            Some(numbers.iter().map(|&x| Some(x)).collect_vec()),
+            None,
+            Some(vec![Some(1.0)]),
+            Some(numbers[50..].iter().map(|&x| Some(x)).collect_vec()),
+        ];
+        let array = ListArray::from_iter_primitive::<Float64Type, _, _>(lists);
+
+        let (output, errors) = catch22_list_array(&array, Default::default()).unwrap();
+
+        assert_eq!(output.num_columns(), 22);
+        assert_eq!(
+            errors,
+            [(
+                2,
+                Catch22Error::SizeUnder {
+                    len: 1,
+                    expect_minimum: 2
+                }
+            )]
+        );
+        assert_eq!(output.column_names()[0], "DN_HistogramMode_5");
+        assert_eq!(
+            (0..4).map(|i| output.is_valid(i)).collect_vec(),
+            [true, false, false, true]
+        );
+        for (row, values) in [(0, &numbers[..]), (3, &numbers[50..])] {
+            let expected = catch22_all(values).unwrap();
+            for (column, &value) in output.columns().iter().zip(expected.values.iter()) {
+                let column = column.as_any().downcast_ref::<Float64Array>().unwrap();
+                assert_eq!(column.value(row), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_catch22_float64_array_same_as_slice() {
+        let numbers = load_test_data::<f64>();
+
+        assert_eq!(
+            catch22_float64_array(&Float64Array::from(numbers.clone()), Default::default())
+                .unwrap(),
+            catch22_all(&numbers).unwrap()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use crate::catch22::{catch22_all_with_planner, extend_row_or_nan, feature_index, Catch22Options};
+use crate::error::Catch22Error;
+use crate::fft::FftPlanner;
+use crate::primitive::Float;
+use itertools::Itertools;
+use rayon::prelude::*;
+
+/// Lengths whose FFT tables each worker keeps, so that batches of series of many
+/// distinct lengths do not accumulate tables.
+const PLAN_LIMIT: usize = 64;
+
+/// Feature matrix of a batch of series, one row per series in input order.
+#[derive(Debug, Clone)]
+pub struct BatchOutput<F> {
+    pub names: Vec<&'static str>,
+    pub short_names: Vec<&'static str>,
+    /// Row major values, `names.len()` per series. The rows of failed series are NaN.
+    pub values: Vec<F>,
+    /// The series whose extraction failed, by index into the input, in ascending order.
+    pub errors: Vec<(usize, Catch22Error)>,
+}
+
+impl<F: Float> BatchOutput<F> {
+    /// Number of series (rows).
+    pub fn len(&self) -> usize {
+        if self.names.is_empty() {
+            0
+        } else {
+            self.values.len() / self.names.len()
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Features of the `index`-th series.
+    pub fn row(&self, index: usize) -> &[F] {
+        &self.values[index * self.names.len()..(index + 1) * self.names.len()]
+    }
+
+    /// A feature of the `index`-th series, by long or short name.
+    pub fn get(&self, index: usize, name: &str) -> Option<F> {
+        let column = feature_index(&self.names, &self.short_names, name)?;
+        self.row(index).get(column).copied()
+    }
+}
+
+/// Computes the catch22 (or catch24) features of every series in parallel.
+///
+/// The rows keep the order of `series` regardless of scheduling. A series that fails
+/// does not abort the batch; its error is reported in [`BatchOutput::errors`].
+pub fn batch<F: Float, S: AsRef<[F]> + Sync>(
+    series: &[S],
+    options: Catch22Options,
+) -> BatchOutput<F> {
+    let features = options.features::<F>();
+
+    let rows = series
+        .par_iter()
+        .map_init(
+            || FftPlanner::with_plan_limit(PLAN_LIMIT),
+            |planner, s| catch22_all_with_planner(s.as_ref(), options, planner),
+        )
+        .collect::<Vec<_>>();
+
+    let mut values = Vec::with_capacity(rows.len() * features.len());
+    let mut errors = vec![];
+    for (i, row) in rows.into_iter().enumerate() {
+        extend_row_or_nan(&mut values, features.len(), &mut errors, i, |values| {
+            values.extend(row?.values);
+            Ok(())
+        });
+    }
+
+    BatchOutput {
+        names: features.iter().map(|f| f.name).collect_vec(),
+        short_names: features.iter().map(|f| f.short_name).collect_vec(),
+        values,
+        errors,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catch22::catch22_all_with;
+    use crate::test_utility::load_test_data;
+
+    #[test]
+    fn test_batch_same_as_sequential() {
+        let numbers = load_test_data::<f64>();
+        let series = (0..40)
+            .map(|i| numbers[i..numbers.len() - i].to_vec())
+            .collect_vec();
+        let options = Catch22Options {
+            catch24: true,
+            ..Default::default()
+        };
+
+        let output = batch(&series, options);
+
+        assert_eq!(output.len(), series.len());
+        assert_eq!(output.names.len(), 24);
+        assert!(output.errors.is_empty());
+        for (i, s) in series.iter().enumerate() {
+            assert_eq!(output.row(i), catch22_all_with(s, options).unwrap().values);
+        }
+    }
+
+    #[test]
+    fn test_batch_reports_errors_per_series() {
+        let numbers = load_test_data::<f64>();
+        let series: Vec<&[f64]> = vec![&numbers, &numbers[..1], &numbers[..100], &[]];
+
+        let output = batch(&series, Catch22Options::default());
+
+        assert_eq!(output.len(), 4);
+        assert_eq!(output.errors.iter().map(|(i, _)| *i).collect_vec(), [1, 3]);
+        assert!(output.row(1).iter().all(|x| x.is_nan()));
+        assert_eq!(
+            output.row(2),
+            catch22_all_with(&numbers[..100], Default::default())
+                .unwrap()
+                .values
+        );
+        assert_eq!(output.get(0, "dfa"), Some(0.75));
+    }
+}
@@ -0,0 +1,557 @@
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use catch22_rs::catch22::{catch22_features, catch24_extra_features, Feature};
+use catch22_rs::missing::{InfinitePolicy, MissingPolicy};
+use catch22_rs::{catch22_select_with_planner, Catch22Options, FftPlanner};
+use clap::{Parser, Subcommand, ValueEnum};
+use itertools::Itertools;
+
+type CliResult<T> = Result<T, Box<dyn Error>>;
+
+#[derive(Parser)]
+#[command(
+    author,
+    version,
+    about = "Extracts catch22 features from time series files"
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Computes the features of every series of the input files, one row per series.
+    Extract(ExtractArgs),
+    /// Lists the available features with their short names.
+    List,
+}
+
+#[derive(clap::Args)]
+struct ExtractArgs {
+    /// Input files; `-` reads standard input.
+    #[arg(required = true, value_name = "FILE")]
+    inputs: Vec<PathBuf>,
+    /// How the series are laid out in the input files.
+    #[arg(short, long, value_enum, default_value_t = Layout::Lines)]
+    layout: Layout,
+    /// Field delimiter of the column and row layouts. Defaults to a comma, tab or
+    /// semicolon if the first non-blank line contains one, and to whitespace otherwise.
+    #[arg(short, long)]
+    delimiter: Option<char>,
+    /// Skip the first line of each input. In the column layout it names the series.
+    #[arg(long)]
+    header: bool,
+    /// Features to compute, by long or short name; the others are not computed. Defaults
+    /// to all of them.
+    #[arg(short, long, value_delimiter = ',', value_name = "NAME")]
+    features: Vec<String>,
+    /// Append the mean and standard deviation (catch24).
+    #[arg(long)]
+    catch24: bool,
+    /// Compute the features of the raw series instead of the z-scored one.
+    #[arg(long)]
+    no_normalize: bool,
+    /// Handling of NaN samples.
+    #[arg(long, value_enum, default_value_t = Missing::Propagate)]
+    missing: Missing,
+    /// Handling of infinite samples.
+    #[arg(long, value_enum, default_value_t = Infinite::Allow)]
+    infinite: Infinite,
+    #[arg(long, value_enum, default_value_t = Format::Csv)]
+    format: Format,
+    /// Name the output columns with the short feature names.
+    #[arg(long)]
+    short_names: bool,
+    /// Output file. Defaults to standard output.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Layout {
+    /// One value per line, the whole file being a single series.
+    Lines,
+    /// One series per column of a delimited table.
+    Columns,
+    /// One series per row of a delimited table.
+    Rows,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Missing {
+    Propagate,
+    Error,
+    Drop,
+    Interpolate,
+    ForwardFill,
+}
+
+impl From<Missing> for MissingPolicy {
+    fn from(missing: Missing) -> Self {
+        match missing {
+            Missing::Propagate => MissingPolicy::Propagate,
+            Missing::Error => MissingPolicy::Error,
+            Missing::Drop => MissingPolicy::Drop,
+            Missing::Interpolate => MissingPolicy::Interpolate,
+            Missing::ForwardFill => MissingPolicy::ForwardFill,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Infinite {
+    Allow,
+    Error,
+    Missing,
+}
+
+impl From<Infinite> for InfinitePolicy {
+    fn from(infinite: Infinite) -> Self {
+        match infinite {
+            Infinite::Allow => InfinitePolicy::Allow,
+            Infinite::Error => InfinitePolicy::Error,
+            Infinite::Missing => InfinitePolicy::Missing,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Format {
+    Csv,
+    Tsv,
+    Json,
+}
+
+/// Identifies a series within its file: its header name or its position.
+#[derive(Debug, Clone, PartialEq)]
+enum Label {
+    Index(usize),
+    Name(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct Series {
+    label: Label,
+    values: Vec<f64>,
+}
+
+struct Row {
+    file: String,
+    label: Label,
+    values: Vec<f64>,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    let result = match cli.command {
+        Command::Extract(args) => extract(&args),
+        Command::List => list(),
+    };
+    if let Err(e) = result {
+        // the output was piped into a program that stopped reading
+        if e.downcast_ref::<io::Error>()
+            .is_some_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+        {
+            return;
+        }
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
+
+fn list() -> CliResult<()> {
+    let mut out = io::stdout().lock();
+    for f in all_features() {
+        writeln!(out, "{}\t{}", f.name, f.short_name)?;
+    }
+    Ok(())
+}
+
+fn all_features() -> Vec<Feature<f64>> {
+    catch22_features()
+        .into_iter()
+        .chain(catch24_extra_features())
+        .collect_vec()
+}
+
+fn extract(args: &ExtractArgs) -> CliResult<()> {
+    let columns = select_features(&args.features, args.catch24)?;
+    let options = Catch22Options {
+        // the catch24 features are only computed in catch24 mode
+        catch24: args.catch24
+            || columns
+                .iter()
+                .any(|&i| i >= catch22_features::<f64>().len()),
+        normalize: !args.no_normalize,
+        missing: args.missing.into(),
+        infinite: args.infinite.into(),
+    };
+    let features = all_features();
+
+    let mut planner = FftPlanner::new();
+    let mut rows = vec![];
+    for input in args.inputs.iter() {
+        let file = input.display().to_string();
+        let text = read_input(input).map_err(|e| format!("{file}: {e}"))?;
+        let series = parse_series(&text, args.layout, args.delimiter, args.header)
+            .map_err(|e| format!("{file}: {e}"))?;
+
+        for Series { label, values } in series {
+            let values = match catch22_select_with_planner(&values, options, &columns, &mut planner)
+            {
+                Ok(values) => values,
+                Err(e) => {
+                    eprintln!("warning: {file}: series {}: {e}", label_text(&label));
+                    vec![f64::NAN; columns.len()]
+                }
+            };
+            rows.push(Row {
+                file: file.clone(),
+                label,
+                values,
+            });
+        }
+    }
+
+    let names = columns
+        .iter()
+        .map(|&i| {
+            if args.short_names {
+                features[i].short_name
+            } else {
+                features[i].name
+            }
+        })
+        .collect_vec();
+    let formatted = format_rows(&names, &rows, args.format);
+    match &args.output {
+        Some(path) => {
+            let mut out = BufWriter::new(File::create(path)?);
+            out.write_all(formatted.as_bytes())?;
+            out.flush()?;
+        }
+        None => io::stdout().lock().write_all(formatted.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Positions of the requested features in [`all_features`], or of every catch22 (or
+/// catch24) feature if none is requested.
+fn select_features(requested: &[String], catch24: bool) -> CliResult<Vec<usize>> {
+    let features = all_features();
+    if requested.is_empty() {
+        let len = if catch24 {
+            features.len()
+        } else {
+            catch22_features::<f64>().len()
+        };
+        return Ok((0..len).collect_vec());
+    }
+
+    let columns = requested
+        .iter()
+        .map(|name| {
+            features
+                .iter()
+                .position(|f| f.name == name || f.short_name == name)
+                .ok_or_else(|| format!("unknown feature `{name}`, see `catch22 list`"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(columns)
+}
+
+fn read_input(path: &Path) -> io::Result<String> {
+    let mut text = String::new();
+    if path.as_os_str() == "-" {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        File::open(path)?.read_to_string(&mut text)?;
+    }
+    Ok(text)
+}
+
+/// Splits the text of a file into its series. Blank lines are skipped. In the column and
+/// row layouts, empty and absent fields within a series are missing (NaN) samples, and
+/// those after its last value are dropped, so series may have different lengths.
+fn parse_series(
+    text: &str,
+    layout: Layout,
+    delimiter: Option<char>,
+    header: bool,
+) -> CliResult<Vec<Series>> {
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+    let delimiter = delimiter.or_else(|| {
+        let first = text
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        [',', '\t', ';'].into_iter().find(|&d| first.contains(d))
+    });
+
+    let header = if header { lines.next() } else { None };
+    let parse = |(i, field): (usize, &str)| {
+        field
+            .parse::<f64>()
+            .map_err(|_| format!("line {}: invalid number `{field}`", i + 1))
+    };
+
+    Ok(match layout {
+        Layout::Lines => {
+            let values = lines
+                .map(|(i, line)| parse((i, line.trim())))
+                .collect::<Result<Vec<_>, _>>()?;
+            vec![Series {
+                label: Label::Index(0),
+                values,
+            }]
+        }
+        Layout::Rows => lines
+            .enumerate()
+            .map(|(index, (i, line))| {
+                let mut fields = split(line, delimiter);
+                while fields.last().is_some_and(|field| field.is_empty()) {
+                    fields.pop();
+                }
+                let values = fields
+                    .into_iter()
+                    .map(|field| match field {
+                        "" => Ok(f64::NAN),
+                        field => parse((i, field)),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Series {
+                    label: Label::Index(index),
+                    values,
+                })
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        Layout::Columns => {
+            let mut series: Vec<Series> = header
+                .map(|(_, line)| {
+                    split(line, delimiter)
+                        .into_iter()
+                        .map(|name| Series {
+                            label: Label::Name(name.to_string()),
+                            values: vec![],
+                        })
+                        .collect_vec()
+                })
+                .unwrap_or_default();
+            // empty fields seen in each column since its last value
+            let mut empty = vec![0; series.len()];
+            for (i, line) in lines {
+                let fields = split(line, delimiter);
+                while series.len() < fields.len() {
+                    series.push(Series {
+                        label: Label::Index(series.len()),
+                        values: vec![],
+                    });
+                }
+                empty.resize(series.len(), 0);
+                for (j, s) in series.iter_mut().enumerate() {
+                    match fields.get(j).filter(|field| !field.is_empty()) {
+                        Some(&field) => {
+                            s.values.extend((0..empty[j]).map(|_| f64::NAN));
+                            s.values.push(parse((i, field))?);
+                            empty[j] = 0;
+                        }
+                        None => empty[j] += 1,
+                    }
+                }
+            }
+            series
+        }
+    })
+}
+
+/// The trimmed fields of `line`, split at `delimiter` or at whitespace if there is none.
+fn split(line: &str, delimiter: Option<char>) -> Vec<&str> {
+    match delimiter {
+        Some(d) => line.split(d).map(str::trim).collect_vec(),
+        None => line.split_whitespace().collect_vec(),
+    }
+}
+
+fn label_text(label: &Label) -> String {
+    match label {
+        Label::Index(i) => i.to_string(),
+        Label::Name(name) => name.clone(),
+    }
+}
+
+fn format_rows(names: &[&str], rows: &[Row], format: Format) -> String {
+    let mut out = String::new();
+    match format {
+        Format::Csv | Format::Tsv => {
+            let (separator, quote): (&str, fn(&str) -> String) = match format {
+                Format::Csv => (",", csv_field),
+                _ => ("\t", |s: &str| s.replace(['\t', '\n'], " ")),
+            };
+            let header = ["file", "series"]
+                .iter()
+                .chain(names.iter())
+                .map(|s| quote(s));
+            let _ = writeln!(out, "{}", header.format(separator));
+            for row in rows {
+                let _ = writeln!(
+                    out,
+                    "{}{separator}{}{separator}{}",
+                    quote(&row.file),
+                    quote(&label_text(&row.label)),
+                    row.values.iter().format(separator)
+                );
+            }
+        }
+        Format::Json => {
+            out.push_str("[\n");
+            for (k, row) in rows.iter().enumerate() {
+                let label = match &row.label {
+                    Label::Index(i) => i.to_string(),
+                    Label::Name(name) => json_string(name),
+                };
+                let features = names
+                    .iter()
+                    .zip(row.values.iter())
+                    .map(|(name, &value)| format!("{}: {}", json_string(name), json_number(value)))
+                    .join(", ");
+                let separator = if k + 1 < rows.len() { "," } else { "" };
+                let _ = writeln!(
+                    out,
+                    "  {{\"file\": {}, \"series\": {label}, {features}}}{separator}",
+                    json_string(&row.file)
+                );
+            }
+            out.push_str("]\n");
+        }
+    }
+    out
+}
+
+/// Quotes a CSV field if it contains a separator, a quote or a line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// JSON has no NaN or infinity; they are written as `null`.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_series_layouts() {
+        let lines = parse_series("1\n2\n\n3\n", Layout::Lines, None, false).unwrap();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].values, [1.0, 2.0, 3.0]);
+
+        let rows = parse_series("1,2,3\n4,5,\n", Layout::Rows, None, false).unwrap();
+        assert_eq!(rows[0].values, [1.0, 2.0, 3.0]);
+        assert_eq!(rows[1].values, [4.0, 5.0]);
+        assert_eq!(rows[1].label, Label::Index(1));
+
+        let columns =
+            parse_series("a\tb\n1\t4\n2\t\n3\tNaN\n", Layout::Columns, None, true).unwrap();
+        assert_eq!(columns[0].label, Label::Name("a".to_string()));
+        assert_eq!(columns[0].values, [1.0, 2.0, 3.0]);
+        assert_eq!(columns[1].values[0], 4.0);
+        assert!(columns[1].values[1..].iter().all(|x| x.is_nan()));
+
+        // interior gaps keep the later samples in place; trailing gaps are trimmed
+        let gaps = parse_series("1,,7\n,5\n3,6,\n4\n", Layout::Columns, None, false).unwrap();
+        assert_eq!(format!("{:?}", gaps[0].values), "[1.0, NaN, 3.0, 4.0]");
+        assert_eq!(format!("{:?}", gaps[1].values), "[NaN, 5.0, 6.0]");
+        assert_eq!(gaps[2].values, [7.0]);
+
+        let gaps = parse_series("1,,3,,\n,5\n", Layout::Rows, None, false).unwrap();
+        assert_eq!(format!("{:?}", gaps[0].values), "[1.0, NaN, 3.0]");
+        assert_eq!(format!("{:?}", gaps[1].values), "[NaN, 5.0]");
+
+        // the delimiter is detected on the first non-blank line
+        let blank = parse_series("\n  \n1;2\n3;4\n", Layout::Rows, None, false).unwrap();
+        assert_eq!(blank[1].values, [3.0, 4.0]);
+
+        let spaces = parse_series("1 2\n3 4\n", Layout::Columns, None, false).unwrap();
+        assert_eq!(spaces[1].values, [2.0, 4.0]);
+
+        assert!(parse_series("1\nx\n", Layout::Lines, None, false).is_err());
+    }
+
+    #[test]
+    fn test_select_features() {
+        assert_eq!(select_features(&[], false).unwrap().len(), 22);
+        assert_eq!(select_features(&[], true).unwrap().len(), 24);
+
+        let requested = ["mode_5", "DN_Spread_Std"].map(String::from);
+        assert_eq!(select_features(&requested, false).unwrap(), [0, 23]);
+
+        assert!(select_features(&["nope".to_string()], false).is_err());
+    }
+
+    #[test]
+    fn test_format_rows() {
+        let rows = [
+            Row {
+                file: "a,b.csv".to_string(),
+                label: Label::Name("x".to_string()),
+                values: vec![0.5, f64::NAN],
+            },
+            Row {
+                file: "c.txt".to_string(),
+                label: Label::Index(1),
+                values: vec![1.0, 2.0],
+            },
+        ];
+        let names = ["mode_5", "mode_10"];
+
+        assert_eq!(
+            format_rows(&names, &rows, Format::Csv),
+            "file,series,mode_5,mode_10\n\"a,b.csv\",x,0.5,NaN\nc.txt,1,1,2\n"
+        );
+        assert_eq!(
+            format_rows(&names, &rows, Format::Tsv),
+            "file\tseries\tmode_5\tmode_10\na,b.csv\tx\t0.5\tNaN\nc.txt\t1\t1\t2\n"
+        );
+        assert_eq!(
+            format_rows(&names, &rows, Format::Json),
+            "[\n  {\"file\": \"a,b.csv\", \"series\": \"x\", \"mode_5\": 0.5, \"mode_10\": null},\n  \
+             {\"file\": \"c.txt\", \"series\": 1, \"mode_5\": 1, \"mode_10\": 2}\n]\n"
+        );
+    }
+}
@@ -0,0 +1,661 @@
+use crate::context::{BufferPool, SeriesContext};
+use crate::error::{Catch22Error, Catch22Result};
+use crate::features::co_auto_corr::{
+    co_embed2_dist_tau_d_expfit_meandiff_with_context, co_f1ecac_with_context,
+    co_firstmin_ac_with_context, co_trev_1_num_with_context,
+    in_auto_mutual_info_stats_40_gaussian_fmmi_with_context,
+};
+use crate::features::co_histogram_ami::co_histogram_ami_even_2_5_with_context;
+use crate::features::dn_histogram_mode::{
+    dn_histogram_mode_10_with_context, dn_histogram_mode_5_with_context,
+};
+use crate::features::dn_moments::{dn_mean_with_context, dn_spread_std_with_context};
+use crate::features::dn_outlier_include_n_001_mdrmd::{
+    dn_outliner_include_n_001_mdrmd_with_context, dn_outliner_include_p_001_mdrmd_with_context,
+};
+use crate::features::fc_local_simple::{
+    fc_local_simple_mean1_tauresrat_with_context, fc_localsimple_mean3_stderr_with_context,
+};
+use crate::features::md_hrv_classic::md_hrv_classic_pnn40_with_context;
+use crate::features::pd_periodicity_wang::pd_periodicity_wang_th0_01_with_context;
+use crate::features::sb_binary_stats::{
+    sb_binarystats_diff_longstretch0_with_context, sb_binarystats_mean_longstretch1_with_context,
+};
+use crate::features::sb_motif_three::sb_motif_three_quantile_hh_with_context;
+use crate::features::sb_transition_matrix::sb_transition_matrix_3ac_sumdiagcov_with_context;
+use crate::features::sc_fluct_anal::{
+    sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1_with_context,
+    sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1_with_context,
+};
+use crate::features::sp_summaries::{
+    sp_summaries_welch_rect_area_5_1_with_context, sp_summaries_welch_rect_centroid_with_context,
+};
+use crate::fft::FftPlanner;
+use crate::missing::{apply_missing_policy_into, InfinitePolicy, MissingPolicy};
+use crate::primitive::Float;
+use crate::utility::zscore_into;
+use itertools::Itertools;
+
+const NUM_CATCH22: usize = 22;
+const NUM_CATCH24_EXTRA: usize = 2;
+
+/// Indices of every feature, selecting them all without allocating.
+const ALL_FEATURES: [usize; NUM_CATCH22 + NUM_CATCH24_EXTRA] = {
+    let mut indices = [0; NUM_CATCH22 + NUM_CATCH24_EXTRA];
+    let mut i = 0;
+    while i < indices.len() {
+        indices[i] = i;
+        i += 1;
+    }
+    indices
+};
+
+pub type FeatureFn<F> = fn(&SeriesContext<F>) -> Catch22Result<F>;
+
+#[derive(Clone, Copy)]
+pub struct Feature<F> {
+    pub name: &'static str,
+    pub short_name: &'static str,
+    pub function: FeatureFn<F>,
+}
+
+const fn feature<F>(
+    name: &'static str,
+    short_name: &'static str,
+    function: FeatureFn<F>,
+) -> Feature<F> {
+    Feature {
+        name,
+        short_name,
+        function,
+    }
+}
+
+/// The catch22 features in their canonical order.
+pub fn catch22_features<F: Float>() -> [Feature<F>; NUM_CATCH22] {
+    [
+        feature(
+            "DN_HistogramMode_5",
+            "mode_5",
+            dn_histogram_mode_5_with_context,
+        ),
+        feature(
+            "DN_HistogramMode_10",
+            "mode_10",
+            dn_histogram_mode_10_with_context,
+        ),
+        feature("CO_f1ecac", "acf_timescale", co_f1ecac_with_context),
+        feature(
+            "CO_FirstMin_ac",
+            "acf_first_min",
+            co_firstmin_ac_with_context,
+        ),
+        feature(
+            "CO_HistogramAMI_even_2_5",
+            "ami2",
+            co_histogram_ami_even_2_5_with_context,
+        ),
+        feature("CO_trev_1_num", "trev", co_trev_1_num_with_context),
+        feature(
+            "MD_hrv_classic_pnn40",
+            "high_fluctuation",
+            md_hrv_classic_pnn40_with_context,
+        ),
+        feature(
+            "SB_BinaryStats_mean_longstretch1",
+            "stretch_high",
+            sb_binarystats_mean_longstretch1_with_context,
+        ),
+        feature(
+            "SB_TransitionMatrix_3ac_sumdiagcov",
+            "transition_matrix",
+            sb_transition_matrix_3ac_sumdiagcov_with_context,
+        ),
+        feature(
+            "PD_PeriodicityWang_th0_01",
+            "periodicity",
+            pd_periodicity_wang_th0_01_with_context,
+        ),
+        feature(
+            "CO_Embed2_Dist_tau_d_expfit_meandiff",
+            "embedding_dist",
+            co_embed2_dist_tau_d_expfit_meandiff_with_context,
+        ),
+        feature(
+            "IN_AutoMutualInfoStats_40_gaussian_fmmi",
+            "ami_timescale",
+            in_auto_mutual_info_stats_40_gaussian_fmmi_with_context,
+        ),
+        feature(
+            "FC_LocalSimple_mean1_tauresrat",
+            "whiten_timescale",
+            fc_local_simple_mean1_tauresrat_with_context,
+        ),
+        feature(
+            "DN_OutlierInclude_p_001_mdrmd",
+            "outlier_timing_pos",
+            dn_outliner_include_p_001_mdrmd_with_context,
+        ),
+        feature(
+            "DN_OutlierInclude_n_001_mdrmd",
+            "outlier_timing_neg",
+            dn_outliner_include_n_001_mdrmd_with_context,
+        ),
+        feature(
+            "SP_Summaries_welch_rect_area_5_1",
+            "low_freq_power",
+            sp_summaries_welch_rect_area_5_1_with_context,
+        ),
+        feature(
+            "SB_BinaryStats_diff_longstretch0",
+            "stretch_decreasing",
+            sb_binarystats_diff_longstretch0_with_context,
+        ),
+        feature(
+            "SB_MotifThree_quantile_hh",
+            "entropy_pairs",
+            sb_motif_three_quantile_hh_with_context,
+        ),
+        feature(
+            "SC_FluctAnal_2_rsrangefit_50_1_logi_prop_r1",
+            "rs_range",
+            sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1_with_context,
+        ),
+        feature(
+            "SC_FluctAnal_2_dfa_50_1_2_logi_prop_r1",
+            "dfa",
+            sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1_with_context,
+        ),
+        feature(
+            "SP_Summaries_welch_rect_centroid",
+            "centroid_freq",
+            sp_summaries_welch_rect_centroid_with_context,
+        ),
+        feature(
+            "FC_LocalSimple_mean3_stderr",
+            "forecast_error",
+            fc_localsimple_mean3_stderr_with_context,
+        ),
+    ]
+}
+
+/// The location and scale features appended in catch24 mode.
+pub fn catch24_extra_features<F: Float>() -> [Feature<F>; NUM_CATCH24_EXTRA] {
+    [
+        feature("DN_Mean", "mean", dn_mean_with_context),
+        feature("DN_Spread_Std", "SD", dn_spread_std_with_context),
+    ]
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Catch22Options {
+    /// Also compute the mean and standard deviation (catch24).
+    pub catch24: bool,
+    /// Z-score the series before computing the catch22 features, as the reference
+    /// implementation does. The catch24 features always see the raw series.
+    pub normalize: bool,
+    /// Handling of NaN samples, applied before anything else.
+    pub missing: MissingPolicy,
+    /// Handling of ±Inf samples, applied before anything else.
+    pub infinite: InfinitePolicy,
+}
+
+impl Default for Catch22Options {
+    fn default() -> Self {
+        Self {
+            catch24: false,
+            normalize: true,
+            missing: MissingPolicy::Propagate,
+            infinite: InfinitePolicy::Allow,
+        }
+    }
+}
+
+impl Catch22Options {
+    /// Number of features computed with these options.
+    pub fn num_features(&self) -> usize {
+        if self.catch24 {
+            NUM_CATCH22 + NUM_CATCH24_EXTRA
+        } else {
+            NUM_CATCH22
+        }
+    }
+
+    /// The features computed with these options, in output order.
+    pub fn features<F: Float>(&self) -> Vec<Feature<F>> {
+        let extra: &[Feature<F>] = if self.catch24 {
+            &catch24_extra_features()
+        } else {
+            &[]
+        };
+        catch22_features()
+            .iter()
+            .chain(extra.iter())
+            .copied()
+            .collect_vec()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Catch22Output<F> {
+    pub names: Vec<&'static str>,
+    pub short_names: Vec<&'static str>,
+    pub values: Vec<F>,
+}
+
+impl<F: Float> Catch22Output<F> {
+    pub fn get(&self, name: &str) -> Option<F> {
+        feature_index(&self.names, &self.short_names, name).map(|i| self.values[i])
+    }
+}
+
+/// Position of the feature named `name`, by long or short name, in the parallel lists
+/// `names` and `short_names`.
+pub(crate) fn feature_index(names: &[&str], short_names: &[&str], name: &str) -> Option<usize> {
+    names
+        .iter()
+        .zip(short_names.iter())
+        .position(|(&n, &s)| n == name || s == name)
+}
+
+/// Appends a row of `width` features to `values` with `extend`. If that fails, the row is
+/// NaN instead and the error is recorded in `errors` under `index`.
+pub(crate) fn extend_row_or_nan<F: Float>(
+    values: &mut Vec<F>,
+    width: usize,
+    errors: &mut Vec<(usize, Catch22Error)>,
+    index: usize,
+    extend: impl FnOnce(&mut Vec<F>) -> Catch22Result<()>,
+) {
+    let start = values.len();
+    if let Err(e) = extend(values) {
+        values.truncate(start);
+        values.extend(std::iter::repeat_n(F::nan(), width));
+        errors.push((index, e));
+    }
+}
+
+/// Computes all catch22 features of `values` in the canonical order.
+pub fn catch22_all<F: Float>(values: &[F]) -> Catch22Result<Catch22Output<F>> {
+    catch22_all_with(values, Catch22Options::default())
+}
+
+/// Computes all catch22 (or catch24) features of `values` in the canonical order.
+pub fn catch22_all_with<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+) -> Catch22Result<Catch22Output<F>> {
+    catch22_all_with_planner(values, options, &mut FftPlanner::new())
+}
+
+/// Like [`catch22_all_with`], reusing the FFT tables and buffers of `planner`. Prefer
+/// this when extracting features from many series.
+pub fn catch22_all_with_planner<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    planner: &mut FftPlanner<F>,
+) -> Catch22Result<Catch22Output<F>> {
+    let mut output = vec![];
+    catch22_extend(
+        values,
+        options,
+        planner,
+        &mut BufferPool::default(),
+        &mut output,
+    )?;
+
+    let features = options.features::<F>();
+    Ok(Catch22Output {
+        names: features.iter().map(|f| f.name).collect_vec(),
+        short_names: features.iter().map(|f| f.short_name).collect_vec(),
+        values: output,
+    })
+}
+
+/// Computes the features at `indices` of [`Catch22Options::features`], in the order of
+/// `indices`, with the same values as [`catch22_all_with`]. An index past the features of
+/// `options` is a [`Catch22Error::InvalidUsizeParameter`].
+pub fn catch22_select<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    indices: &[usize],
+) -> Catch22Result<Vec<F>> {
+    catch22_select_with_planner(values, options, indices, &mut FftPlanner::new())
+}
+
+/// Like [`catch22_select`], reusing the FFT tables and buffers of `planner`.
+pub fn catch22_select_with_planner<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    indices: &[usize],
+    planner: &mut FftPlanner<F>,
+) -> Catch22Result<Vec<F>> {
+    let mut output = vec![];
+    catch22_extend_select(
+        values,
+        options,
+        indices,
+        planner,
+        &mut BufferPool::default(),
+        &mut output,
+    )?;
+    Ok(output)
+}
+
+/// Appends the features of `values` to `output`, taking the working memory from `pool`
+/// and giving it back, so that repeated extraction can reuse the allocations. `output` is
+/// left unchanged on error.
+pub(crate) fn catch22_extend<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    planner: &mut FftPlanner<F>,
+    pool: &mut BufferPool<F>,
+    output: &mut Vec<F>,
+) -> Catch22Result<()> {
+    let indices = &ALL_FEATURES[..options.num_features()];
+    catch22_extend_select(values, options, indices, planner, pool, output)
+}
+
+/// Same as [`catch22_extend`] for the features at `indices` only.
+fn catch22_extend_select<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    indices: &[usize],
+    planner: &mut FftPlanner<F>,
+    pool: &mut BufferPool<F>,
+    output: &mut Vec<F>,
+) -> Catch22Result<()> {
+    if let Some(&index) = indices.iter().find(|&&i| i >= options.num_features()) {
+        return Err(Catch22Error::InvalidUsizeParameter {
+            name: "feature",
+            value: index,
+        });
+    }
+
+    let mut cleaned = pool.take_floats();
+    let mut normalized = pool.take_floats();
+    let start = output.len();
+    let result = extend_with(
+        values,
+        options,
+        indices,
+        planner,
+        pool,
+        &mut cleaned,
+        &mut normalized,
+        output,
+    );
+    if result.is_err() {
+        output.truncate(start);
+    }
+    pool.give_floats(normalized);
+    pool.give_floats(cleaned);
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+fn extend_with<F: Float>(
+    values: &[F],
+    options: Catch22Options,
+    indices: &[usize],
+    planner: &mut FftPlanner<F>,
+    pool: &mut BufferPool<F>,
+    cleaned: &mut Vec<F>,
+    normalized: &mut Vec<F>,
+    output: &mut Vec<F>,
+) -> Catch22Result<()> {
+    let values = if apply_missing_policy_into(values, options.missing, options.infinite, cleaned)? {
+        &cleaned[..]
+    } else {
+        values
+    };
+    // only the catch22 features see the normalized series
+    let catch22_input = if options.normalize && indices.iter().any(|&i| i < NUM_CATCH22) {
+        zscore_into(values, normalized).ok_or(Catch22Error::SizeUnder {
+            len: values.len(),
+            expect_minimum: 2,
+        })?;
+        &normalized[..]
+    } else {
+        values
+    };
+
+    let catch22 = catch22_features();
+    let extra = catch24_extra_features();
+    let catch22_ctx = SeriesContext::with_pool(catch22_input, planner, std::mem::take(pool));
+    let raw_ctx = SeriesContext::new(values);
+    let result = indices.iter().try_for_each(|&i| {
+        let value = match i.checked_sub(NUM_CATCH22) {
+            None => (catch22[i].function)(&catch22_ctx)?,
+            Some(j) => (extra[j].function)(&raw_ctx)?,
+        };
+        output.push(value);
+        Ok(())
+    });
+    *pool = catch22_ctx.into_pool();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::{arbitrary_series, load_test_data};
+    use crate::utility::zscore;
+    use approx::{assert_abs_diff_eq, assert_relative_eq};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_catch22_all_same_as_individual_features() {
+        let numbers = load_test_data::<f64>();
+
+        let output = catch22_all(&numbers).unwrap();
+
+        assert_eq!(output.values.len(), 22);
+        assert_eq!(output.names[0], "DN_HistogramMode_5");
+        assert_eq!(output.short_names[21], "forecast_error");
+        let normalized = zscore(&numbers).unwrap();
+        for (feature, &value) in catch22_features().iter().zip(output.values.iter()) {
+            let ctx = SeriesContext::new(&normalized);
+            assert_eq!((feature.function)(&ctx).unwrap(), value);
+        }
+        assert_abs_diff_eq!(
+            output.get("CO_f1ecac").unwrap(),
+            32.50260547693647,
+            epsilon = 1e-11
+        );
+        assert_abs_diff_eq!(output.get("dfa").unwrap(), 0.75, epsilon = 1e-12);
+    }
+
+    #[test]
+    fn test_catch22_all_f32_close_to_f64() {
+        let numbers = load_test_data::<f64>();
+        let numbers_f32 = load_test_data::<f32>();
+
+        let expected = catch22_all(&numbers).unwrap();
+        let output = catch22_all(&numbers_f32).unwrap();
+
+        assert_eq!(output.names, expected.names);
+        for (&value, &expected) in output.values.iter().zip(expected.values.iter()) {
+            assert_abs_diff_eq!(
+                value as f64,
+                expected,
+                epsilon = 1e-4 * expected.abs().max(1.0)
+            );
+        }
+    }
+
+    /// Output of the reference implementation on the z-scored test data, in canonical order.
+    const REFERENCE: [f64; 22] = [
+        -0.61479911484527,
+        -0.78225446555221,
+        32.50260547693647,
+        77.0,
+        1.00638907799376,
+        0.00001782472612,
+        0.31970260223048,
+        88.0,
+        0.08,
+        0.0,
+        7.1350786087885,
+        40.0,
+        0.84782608695652,
+        0.40740740740741,
+        -0.23703703703704,
+        0.99313877887424,
+        83.0,
+        1.21058781724385,
+        0.29545454545455,
+        0.75,
+        0.03681553890926,
+        0.08029384289851,
+    ];
+
+    #[test]
+    fn test_catch22_all_f32_same_original() {
+        let output = catch22_all(&load_test_data::<f32>()).unwrap();
+
+        assert_eq!(output.values.len(), REFERENCE.len());
+        for (&value, &expected) in output.values.iter().zip(REFERENCE.iter()) {
+            assert_relative_eq!(value as f64, expected, max_relative = 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_catch22_all_with_planner_same_as_without() {
+        let numbers = load_test_data::<f64>();
+        let mut planner = FftPlanner::new();
+
+        for len in [numbers.len(), 150, numbers.len()] {
+            let values = &numbers[..len];
+            assert_eq!(
+                catch22_all_with_planner(values, Catch22Options::default(), &mut planner).unwrap(),
+                catch22_all(values).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_policy_applied_before_features() {
+        let numbers = load_test_data::<f64>();
+        let mut with_gaps = numbers.clone();
+        with_gaps[10] = f64::NAN;
+        with_gaps[200] = f64::INFINITY;
+
+        assert!(catch22_all(&with_gaps)
+            .unwrap()
+            .values
+            .iter()
+            .all(|x| x.is_nan()));
+
+        let options = Catch22Options {
+            missing: MissingPolicy::Interpolate,
+            infinite: InfinitePolicy::Missing,
+            ..Default::default()
+        };
+        let mut interpolated = numbers.clone();
+        interpolated[10] = (numbers[9] + numbers[11]) / 2.0;
+        interpolated[200] = (numbers[199] + numbers[201]) / 2.0;
+        assert_eq!(
+            catch22_all_with(&with_gaps, options).unwrap(),
+            catch22_all(&interpolated).unwrap()
+        );
+
+        let options = Catch22Options {
+            infinite: InfinitePolicy::Error,
+            ..Default::default()
+        };
+        assert!(matches!(
+            catch22_all_with(&with_gaps, options),
+            Err(Catch22Error::InfiniteValue(200))
+        ));
+    }
+
+    #[test]
+    fn test_catch24_appends_mean_and_std() {
+        let numbers = load_test_data::<f64>();
+
+        let shifted = numbers.iter().map(|&x| x * 3.0 + 2.0).collect_vec();
+        let options = Catch22Options {
+            catch24: true,
+            ..Default::default()
+        };
+        let output = catch22_all_with(&shifted, options).unwrap();
+
+        assert_eq!(output.values.len(), 24);
+        assert_eq!(output.values[..22], catch22_all(&shifted).unwrap().values);
+        assert_eq!(output.names[22..], ["DN_Mean", "DN_Spread_Std"]);
+        assert_abs_diff_eq!(output.get("mean").unwrap(), 2.0, epsilon = 1e-5);
+        assert_abs_diff_eq!(output.get("SD").unwrap(), 3.0, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn test_normalization_removes_location_and_scale() {
+        let numbers = load_test_data::<f64>();
+        let shifted = numbers.iter().map(|&x| x * 10.0 + 5.0).collect_vec();
+
+        let output = catch22_all(&shifted).unwrap();
+        assert_abs_diff_eq!(
+            output.get("DN_HistogramMode_5").unwrap(),
+            -0.61479911484527,
+            epsilon = 1e-5
+        );
+
+        let options = Catch22Options {
+            normalize: false,
+            ..Default::default()
+        };
+        let output = catch22_all_with(&shifted, options).unwrap();
+        assert_abs_diff_eq!(
+            output.get("DN_HistogramMode_5").unwrap(),
+            -0.61479911484527 * 10.0 + 5.0,
+            epsilon = 1e-4
+        );
+    }
+
+    #[test]
+    fn test_catch22_select_same_as_full_extraction() {
+        let numbers = load_test_data::<f64>();
+        let options = Catch22Options {
+            catch24: true,
+            ..Default::default()
+        };
+        let all = catch22_all_with(&numbers, options).unwrap().values;
+
+        let indices = [23, 0, 12, 22, 12, 21];
+        let selected = catch22_select(&numbers, options, &indices).unwrap();
+
+        assert_eq!(selected, indices.iter().map(|&i| all[i]).collect_vec());
+        // the catch24 features alone need no normalization
+        assert_eq!(catch22_select(&[1.0], options, &[22]), Ok(vec![1.0]));
+        assert_eq!(catch22_select(&numbers, options, &[]), Ok(vec![]));
+        assert_eq!(
+            catch22_select(&numbers, Catch22Options::default(), &[22]),
+            Err(Catch22Error::InvalidUsizeParameter {
+                name: "feature",
+                value: 22
+            })
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_features_never_panic(values in arbitrary_series()) {
+            let ctx = SeriesContext::new(&values);
+            for feature in catch22_features().iter().chain(catch24_extra_features().iter()) {
+                let _ = (feature.function)(&ctx);
+            }
+        }
+
+        #[test]
+        fn test_catch22_all_never_panics(values in arbitrary_series(), catch24: bool, normalize: bool) {
+            let options = Catch22Options {
+                catch24,
+                normalize,
+                ..Default::default()
+            };
+            if let Ok(output) = catch22_all_with(&values, options) {
+                prop_assert_eq!(output.values.len(), output.names.len());
+            }
+        }
+    }
+}
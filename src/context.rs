@@ -0,0 +1,274 @@
+use crate::error::Catch22Result;
+use crate::features::co_auto_corr::{autocorrs_into, first_zero};
+use crate::fft::FftPlanner;
+use crate::primitive::Float;
+use crate::utility::{mean, stddev, total_cmp};
+use num::Complex;
+use std::cell::{OnceCell, RefCell};
+use std::ops::{Deref, DerefMut};
+
+/// Buffers lent to the features by a [`SeriesContext`], kept from one series to the next
+/// so that their allocations are reused.
+#[derive(Default)]
+pub(crate) struct BufferPool<F> {
+    /// Capacity of the buffers created when the pool runs empty.
+    pub capacity: usize,
+    floats: Vec<Vec<F>>,
+    indices: Vec<Vec<usize>>,
+    complexes: Vec<Vec<Complex<F>>>,
+}
+
+impl<F> BufferPool<F> {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            capacity,
+            floats: vec![],
+            indices: vec![],
+            complexes: vec![],
+        }
+    }
+
+    pub fn take_floats(&mut self) -> Vec<F> {
+        take(&mut self.floats, self.capacity)
+    }
+
+    pub fn give_floats(&mut self, buffer: Vec<F>) {
+        self.floats.push(buffer);
+    }
+
+    fn floats(&mut self) -> &mut Vec<Vec<F>> {
+        &mut self.floats
+    }
+
+    fn indices(&mut self) -> &mut Vec<Vec<usize>> {
+        &mut self.indices
+    }
+
+    fn complexes(&mut self) -> &mut Vec<Vec<Complex<F>>> {
+        &mut self.complexes
+    }
+}
+
+fn take<T>(buffers: &mut Vec<Vec<T>>, capacity: usize) -> Vec<T> {
+    let mut buffer = buffers
+        .pop()
+        .unwrap_or_else(|| Vec::with_capacity(capacity));
+    buffer.clear();
+    buffer
+}
+
+/// An empty buffer borrowed from the [`BufferPool`] of a context, given back when dropped.
+pub(crate) struct Buffer<'c, F, T> {
+    buffer: Vec<T>,
+    pool: &'c RefCell<BufferPool<F>>,
+    list: fn(&mut BufferPool<F>) -> &mut Vec<Vec<T>>,
+}
+
+impl<'c, F, T> Buffer<'c, F, T> {
+    fn new(
+        pool: &'c RefCell<BufferPool<F>>,
+        list: fn(&mut BufferPool<F>) -> &mut Vec<Vec<T>>,
+    ) -> Self {
+        let buffer = {
+            let mut pool = pool.borrow_mut();
+            let capacity = pool.capacity;
+            take(list(&mut pool), capacity)
+        };
+        Self { buffer, pool, list }
+    }
+}
+
+impl<F, T> Deref for Buffer<'_, F, T> {
+    type Target = Vec<T>;
+
+    fn deref(&self) -> &Vec<T> {
+        &self.buffer
+    }
+}
+
+impl<F, T> DerefMut for Buffer<'_, F, T> {
+    fn deref_mut(&mut self) -> &mut Vec<T> {
+        &mut self.buffer
+    }
+}
+
+impl<F, T> Drop for Buffer<'_, F, T> {
+    fn drop(&mut self) {
+        let buffer = std::mem::take(&mut self.buffer);
+        (self.list)(&mut self.pool.borrow_mut()).push(buffer);
+    }
+}
+
+/// A series together with lazily computed intermediate results shared by several features.
+///
+/// Each intermediate is computed at most once, the first time a feature asks for it.
+pub struct SeriesContext<'a, F> {
+    values: &'a [F],
+    planner: Option<RefCell<&'a mut FftPlanner<F>>>,
+    pool: RefCell<BufferPool<F>>,
+    autocorrs: OnceCell<Catch22Result<Vec<F>>>,
+    first_zero: OnceCell<Catch22Result<usize>>,
+    sorted: OnceCell<Vec<F>>,
+    mean: OnceCell<Option<F>>,
+    stddev: OnceCell<Option<F>>,
+}
+
+impl<'a, F: Float> SeriesContext<'a, F> {
+    pub fn new(values: &'a [F]) -> Self {
+        Self::with_optional_planner(values, None, BufferPool::default())
+    }
+
+    /// Like [`SeriesContext::new`], but computes the FFT based intermediates with
+    /// `planner`, so that its cached tables and buffers are reused across series.
+    pub fn with_planner(values: &'a [F], planner: &'a mut FftPlanner<F>) -> Self {
+        Self::with_optional_planner(values, Some(planner), BufferPool::default())
+    }
+
+    /// Like [`SeriesContext::with_planner`], lending the buffers of `pool` to the features.
+    /// [`SeriesContext::into_pool`] gives them back, together with the cached intermediates.
+    pub(crate) fn with_pool(
+        values: &'a [F],
+        planner: &'a mut FftPlanner<F>,
+        pool: BufferPool<F>,
+    ) -> Self {
+        Self::with_optional_planner(values, Some(planner), pool)
+    }
+
+    fn with_optional_planner(
+        values: &'a [F],
+        planner: Option<&'a mut FftPlanner<F>>,
+        pool: BufferPool<F>,
+    ) -> Self {
+        Self {
+            values,
+            planner: planner.map(RefCell::new),
+            pool: RefCell::new(pool),
+            autocorrs: OnceCell::new(),
+            first_zero: OnceCell::new(),
+            sorted: OnceCell::new(),
+            mean: OnceCell::new(),
+            stddev: OnceCell::new(),
+        }
+    }
+
+    pub fn values(&self) -> &'a [F] {
+        self.values
+    }
+
+    /// Returns the buffers lent by [`SeriesContext::with_pool`].
+    pub(crate) fn into_pool(self) -> BufferPool<F> {
+        let mut pool = self.pool.into_inner();
+        if let Some(Ok(autocorrs)) = self.autocorrs.into_inner() {
+            pool.give_floats(autocorrs);
+        }
+        if let Some(sorted) = self.sorted.into_inner() {
+            pool.give_floats(sorted);
+        }
+        pool
+    }
+
+    pub(crate) fn floats(&self) -> Buffer<'_, F, F> {
+        Buffer::new(&self.pool, BufferPool::floats)
+    }
+
+    pub(crate) fn indices(&self) -> Buffer<'_, F, usize> {
+        Buffer::new(&self.pool, BufferPool::indices)
+    }
+
+    pub(crate) fn complexes(&self) -> Buffer<'_, F, Complex<F>> {
+        Buffer::new(&self.pool, BufferPool::complexes)
+    }
+
+    /// Runs `f` with the planner of the context, or with a new one if it has none.
+    pub(crate) fn with_fft_planner<R>(&self, f: impl FnOnce(&mut FftPlanner<F>) -> R) -> R {
+        match &self.planner {
+            Some(planner) => f(&mut planner.borrow_mut()),
+            None => f(&mut FftPlanner::new()),
+        }
+    }
+
+    /// Autocorrelation function of the series, zero padded to the FFT length.
+    pub fn autocorrs(&self) -> Catch22Result<&[F]> {
+        self.autocorrs
+            .get_or_init(|| {
+                let mut ac = self.pool.borrow_mut().take_floats();
+                match self.autocorrs_into(self.values, &mut ac) {
+                    Ok(()) => Ok(ac),
+                    Err(e) => {
+                        self.pool.borrow_mut().give_floats(ac);
+                        Err(e)
+                    }
+                }
+            })
+            .as_deref()
+            .map_err(Clone::clone)
+    }
+
+    /// Autocorrelation function of `values` (which need not be the series of the context),
+    /// computed with the planner and buffers of the context.
+    pub(crate) fn autocorrs_into(&self, values: &[F], ac: &mut Vec<F>) -> Catch22Result<()> {
+        let mut spectrum = self.complexes();
+        self.with_fft_planner(|planner| autocorrs_into(values, planner, &mut spectrum, ac))
+    }
+
+    /// First zero crossing of the autocorrelation function.
+    pub fn first_zero(&self) -> Catch22Result<usize> {
+        self.first_zero
+            .get_or_init(|| Ok(first_zero(self.autocorrs()?, self.values.len())))
+            .clone()
+    }
+
+    /// The values in ascending order.
+    pub fn sorted(&self) -> &[F] {
+        self.sorted.get_or_init(|| {
+            let mut sorted = self.pool.borrow_mut().take_floats();
+            sorted.extend_from_slice(self.values);
+            sorted.sort_unstable_by(total_cmp);
+            sorted
+        })
+    }
+
+    pub fn mean(&self) -> Option<F> {
+        *self.mean.get_or_init(|| mean(self.values))
+    }
+
+    /// Sample standard deviation.
+    pub fn stddev(&self) -> Option<F> {
+        *self.stddev.get_or_init(|| stddev(self.values))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::co_auto_corr::{co_autocorrs, co_firstzero};
+    use crate::test_utility::load_test_data;
+    use crate::utility::stddev;
+
+    #[test]
+    fn test_series_context_same_as_direct_computation() {
+        let numbers = load_test_data::<f64>();
+        let ctx = SeriesContext::new(&numbers);
+
+        assert_eq!(ctx.autocorrs().unwrap(), co_autocorrs(&numbers).unwrap());
+        assert_eq!(
+            ctx.first_zero().unwrap(),
+            co_firstzero(&numbers, numbers.len()).unwrap()
+        );
+        assert!(ctx.sorted().windows(2).all(|w| w[0] <= w[1]));
+        assert_eq!(ctx.mean(), mean(&numbers));
+        assert_eq!(ctx.stddev(), stddev(&numbers));
+    }
+
+    #[test]
+    fn test_series_context_with_planner_same_autocorrs() {
+        let numbers = load_test_data::<f64>();
+        let mut planner = FftPlanner::new();
+
+        for len in [numbers.len(), 100, numbers.len()] {
+            let values = &numbers[..len];
+            let ctx = SeriesContext::with_planner(values, &mut planner);
+            assert_eq!(ctx.autocorrs().unwrap(), co_autocorrs(values).unwrap());
+        }
+    }
+}
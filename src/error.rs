@@ -1,7 +1,6 @@
-use crate::primitive::Float;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, PartialEq, Error)]
 pub enum Catch22Error {
     #[error("Empty input")]
     EmptyInput,
@@ -9,10 +8,18 @@ pub enum Catch22Error {
     SizeOver(usize),
     #[error("Size Under: {len} < {expect_minimum}")]
     SizeUnder { len: usize, expect_minimum: usize },
+    #[error("Length Mismatch: {0} != {1}")]
+    LengthMismatch(usize, usize),
     #[error("Invalid Parameter: {name} {value}")]
-    InvalidFloatParameter { name: &'static str, value: Float },
+    InvalidFloatParameter { name: &'static str, value: f64 },
     #[error("Invalid Parameter: {name} {value}")]
     InvalidUsizeParameter { name: &'static str, value: usize },
+    #[error("Missing Value at {0}")]
+    MissingValue(usize),
+    #[error("Infinite Value at {0}")]
+    InfiniteValue(usize),
+    #[error("Unsupported Data Type: {0}")]
+    UnsupportedDataType(String),
 }
 
 pub type Catch22Result<T> = Result<T, Catch22Error>;
@@ -1,8 +1,13 @@
 pub mod co_auto_corr;
+pub mod co_histogram_ami;
 pub mod dn_histogram_mode;
+pub mod dn_moments;
 pub mod dn_outlier_include_n_001_mdrmd;
 pub mod fc_local_simple;
+pub mod md_hrv_classic;
+pub mod pd_periodicity_wang;
 pub mod sb_binary_stats;
 pub mod sb_motif_three;
+pub mod sb_transition_matrix;
 pub mod sc_fluct_anal;
 pub mod sp_summaries;
@@ -1,43 +1,75 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
-use crate::fft::{fft, twiddles};
-use crate::hist_count::hist_count_flexible;
+use crate::fft::{next_fast_len, FftPlanner};
+use crate::hist_count::hist_count_flexible_into;
 use crate::primitive::Float;
-use crate::utility::{any_nan, mean, mean_iter, nextpow2, stddev};
+use crate::stat::autocorr_lag;
+use crate::utility::{any_nan, mean, mean_iter, stddev, total_cmp};
 use itertools::Itertools;
-use num::{Complex, Zero};
-use std::f64::consts::E;
-use unwrap_ord::UnwrapOrd;
+use num::Complex;
 
-pub fn co_embed2_dist_tau_d_expfit_meandiff(y: &[Float]) -> Catch22Result<Float> {
+pub fn co_embed2_dist_tau_d_expfit_meandiff<F: Float>(y: &[F]) -> Catch22Result<F> {
+    co_embed2_dist_tau_d_expfit_meandiff_with_context(&SeriesContext::new(y))
+}
+
+pub fn co_embed2_dist_tau_d_expfit_meandiff_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    let y = ctx.values();
     if any_nan(y) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let tau = co_firstzero(y, y.len())?.min(y.len() / 10);
-
-    let d = (0..(y.len() - tau - 1))
-        .map(|i| ((y[i + 1] - y[i]).powi(2) + (y[i + tau] - y[i + tau + 1]).powi(2)).sqrt())
-        .collect_vec();
-    let dl = mean_iter(d.iter().copied()).unwrap();
-
-    let bin_data = hist_count_flexible(&d, num_bins_auto(&d).unwrap())?;
-    let bin_data = bin_data.normalize(d.len())?;
+    if y.len() < 2 {
+        return Err(Catch22Error::SizeUnder {
+            len: y.len(),
+            expect_minimum: 2,
+        });
+    }
 
-    Ok(mean_iter((0..bin_data.len()).map(|i| {
-        let exp = (-(bin_data.edges[i] + bin_data.edges[i + 1]) * 0.5 / dl).exp() / dl;
-        (bin_data.count[i] - exp.max(0.0)).abs()
+    let tau = ctx.first_zero()?.min(y.len() / 10);
+
+    let mut d = ctx.floats();
+    d.extend(
+        (0..(y.len() - tau - 1))
+            .map(|i| ((y[i + 1] - y[i]).powi(2) + (y[i + tau] - y[i + tau + 1]).powi(2)).sqrt()),
+    );
+    let dl = mean_iter(d.iter().copied()).ok_or(Catch22Error::SizeUnder {
+        len: y.len(),
+        expect_minimum: tau + 2,
+    })?;
+
+    // a distance distribution too narrow (or too wide) to bin gives no fit, as in the
+    // reference implementation
+    let num_bins = match num_bins_auto(&d) {
+        Some(0) => return Ok(F::zero()),
+        Some(num_bins) => num_bins,
+        None => return Ok(F::nan()),
+    };
+    let mut count = ctx.indices();
+    let (min, bin_step) = hist_count_flexible_into(&d, num_bins, &mut count)?;
+    let edge = |i: usize| F::from_usize(i) * bin_step + min;
+
+    Ok(mean_iter(count.iter().enumerate().map(|(i, &c)| {
+        let exp = (-(edge(i) + edge(i + 1)) * F::from_f64(0.5) / dl).exp() / dl;
+        (F::from_usize(c) / F::from_usize(d.len()) - exp.max(F::zero())).abs()
     }))
-    .unwrap())
+    .unwrap_or(F::nan()))
+}
+
+pub fn co_f1ecac<F: Float>(values: &[F]) -> Catch22Result<F> {
+    co_f1ecac_with_context(&SeriesContext::new(values))
 }
 
-pub fn co_f1ecac(values: &[Float]) -> Catch22Result<Float> {
+pub fn co_f1ecac_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    let values = ctx.values();
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let auto_corr = &co_autocorrs(values)?[..values.len()];
+    let auto_corr = &ctx.autocorrs()?[..values.len()];
 
-    let threshold = 1.0 / E;
+    let threshold = F::one() / F::E();
 
     Ok(auto_corr
         .iter()
@@ -48,37 +80,85 @@ pub fn co_f1ecac(values: &[Float]) -> Catch22Result<Float> {
                 let m = an - a;
                 let dy = threshold - a;
                 let dx = dy / m;
-                (i as Float) + dx
+                F::from_usize(i) + dx
             })
         })
-        .unwrap_or(values.len() as Float))
+        .unwrap_or(F::from_usize(values.len())))
 }
 
-pub fn co_firstmin_ac(values: &[Float]) -> Catch22Result<Float> {
+pub fn co_firstmin_ac<F: Float>(values: &[F]) -> Catch22Result<F> {
+    co_firstmin_ac_with_context(&SeriesContext::new(values))
+}
+
+pub fn co_firstmin_ac_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    let values = ctx.values();
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let auto_corr = &co_autocorrs(values)?[..values.len()];
+    let auto_corr = &ctx.autocorrs()?[..values.len()];
 
     Ok(auto_corr
         .iter()
-        .enumerate()
-        .skip(1)
-        .find_map(|(i, &a)| (a < auto_corr[i - 1] && a < auto_corr[i + 1]).then_some(i as Float))
-        .unwrap_or(values.len() as Float))
+        .tuple_windows()
+        .position(|(&prev, &a, &next)| a < prev && a < next)
+        .map(|i| F::from_usize(i + 1))
+        .unwrap_or(F::from_usize(values.len())))
 }
 
-pub fn co_trev_1_num(values: &[Float]) -> Catch22Result<Float> {
+pub fn in_auto_mutual_info_stats_40_gaussian_fmmi<F: Float>(values: &[F]) -> Catch22Result<F> {
+    in_auto_mutual_info_stats_40_gaussian_fmmi_with_context(&SeriesContext::new(values))
+}
+
+pub fn in_auto_mutual_info_stats_40_gaussian_fmmi_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    in_auto_mutual_info_stats_gaussian_fmmi(ctx, 40)
+}
+
+fn in_auto_mutual_info_stats_gaussian_fmmi<F: Float>(
+    ctx: &SeriesContext<F>,
+    max_tau: usize,
+) -> Catch22Result<F> {
+    let values = ctx.values();
+    if values.is_empty() {
+        return Err(Catch22Error::EmptyInput);
+    }
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+
+    // don't go above half the signal length
+    let tau = max_tau.min(values.len().div_ceil(2));
+
+    // the Pearson correlation of the overlapping parts at each lag, as in the reference
+    // implementation, rather than the autocorrelation function
+    Ok((1..=tau)
+        .map(|lag| {
+            let ac = autocorr_lag(values, lag);
+            -F::from_f64(0.5) * (F::one() - ac * ac).ln()
+        })
+        .tuple_windows()
+        .position(|(prev, a, next)| a < prev && a < next)
+        .map(|i| F::from_usize(i + 1))
+        .unwrap_or(F::from_usize(tau)))
+}
+
+pub fn co_trev_1_num<F: Float>(values: &[F]) -> Catch22Result<F> {
+    co_trev_1_num_with_context(&SeriesContext::new(values))
+}
+
+pub fn co_trev_1_num_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    let values = ctx.values();
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
     mean_iter(
         values
             .iter()
             .zip(values.iter().skip(1))
-            .map(|(a, an)| (an - a).powi(3)),
+            .map(|(&a, &an)| (an - a).powi(3)),
     )
     .ok_or(Catch22Error::SizeUnder {
         len: values.len(),
@@ -86,55 +166,72 @@ pub fn co_trev_1_num(values: &[Float]) -> Catch22Result<Float> {
     })
 }
 
-fn co_autocorrs(values: &[Float]) -> Catch22Result<Vec<Float>> {
-    let mean = mean(values).unwrap();
-    let n_fft = nextpow2(values.len())
-        .and_then(|s| s.checked_shl(1))
-        .ok_or(Catch22Error::SizeOver(values.len()))?;
+pub(crate) fn co_autocorrs<F: Float>(values: &[F]) -> Catch22Result<Vec<F>> {
+    co_autocorrs_with_planner(values, &mut FftPlanner::new())
+}
 
-    let mut f = values
-        .iter()
-        .map(|y| Complex::<Float>::new(y - mean, 0.0))
-        .chain((values.len()..n_fft).map(|_| Complex::zero()))
-        .collect_vec();
+pub(crate) fn co_autocorrs_with_planner<F: Float>(
+    values: &[F],
+    planner: &mut FftPlanner<F>,
+) -> Catch22Result<Vec<F>> {
+    let mut ac = vec![];
+    autocorrs_into(values, planner, &mut vec![], &mut ac)?;
+    Ok(ac)
+}
 
-    let tw = twiddles(n_fft);
-    fft(&mut f, n_fft, &tw);
-    let mut f = f.iter().map(|x| x * x.conj()).collect_vec();
-    fft(&mut f, n_fft, &tw);
+/// Writes the autocorrelation function of `values` into `ac`, using `spectrum` as
+/// working memory.
+pub(crate) fn autocorrs_into<F: Float>(
+    values: &[F],
+    planner: &mut FftPlanner<F>,
+    spectrum: &mut Vec<Complex<F>>,
+    ac: &mut Vec<F>,
+) -> Catch22Result<()> {
+    let mean = mean(values).ok_or(Catch22Error::EmptyInput)?;
+    // zero padding to at least twice the length keeps the circular correlation from
+    // wrapping around
+    let n_fft = next_fast_len(values.len())
+        .and_then(|s| s.checked_mul(2))
+        .ok_or(Catch22Error::SizeOver(values.len()))?;
+
+    ac.clear();
+    ac.extend(values.iter().map(|&y| y - mean));
+    ac.resize(n_fft, F::zero());
 
-    let divisor = f.first().unwrap();
+    planner.real_fft(ac, spectrum);
+    spectrum
+        .iter_mut()
+        .for_each(|x| *x = Complex::new(x.norm_sqr(), F::zero()));
+    planner.inverse_real_fft(spectrum, n_fft, ac)?;
 
-    Ok(f.iter().map(|x| (x / divisor).re).collect_vec())
+    let divisor = ac[0];
+    ac.iter_mut().for_each(|x| *x /= divisor);
+    Ok(())
 }
 
-pub fn co_firstzero(y: &[Float], max_tau: usize) -> Catch22Result<usize> {
-    let ac = co_autocorrs(y)?;
+pub fn co_firstzero<F: Float>(y: &[F], max_tau: usize) -> Catch22Result<usize> {
+    Ok(first_zero(&co_autocorrs(y)?, max_tau))
+}
 
-    let (index, _) = ac
-        .iter()
+pub(crate) fn first_zero<F: Float>(ac: &[F], max_tau: usize) -> usize {
+    ac.iter()
         .enumerate()
-        .find_position(|&(i, &x)| !(x > 0.0 && i < max_tau))
-        .unwrap();
-
-    Ok(index)
+        .position(|(i, &x)| !(x > F::zero() && i < max_tau))
+        .unwrap_or(ac.len())
 }
 
-pub fn num_bins_auto(values: &[Float]) -> Option<usize> {
-    let (min, max) = values
-        .iter()
-        .copied()
-        .minmax_by_key(|&a| UnwrapOrd(a))
-        .into_option()?;
+pub fn num_bins_auto<F: Float>(values: &[F]) -> Option<usize> {
+    let (min, max) = values.iter().copied().minmax_by(total_cmp).into_option()?;
 
     let s = stddev(values)?;
 
-    Some(if s < 0.001 {
-        0
-    } else {
-        let size_pow = (values.len() as Float).powf(1.0 / 3.0);
-        ((max - min) / (3.5 * s / size_pow)).ceil() as usize
-    })
+    if s < F::from_f64(0.001) {
+        return Some(0);
+    }
+    let size_pow = F::from_usize(values.len()).powf(F::one() / F::from_f64(3.0));
+    let num_bins = ((max - min) / (F::from_f64(3.5) * s / size_pow)).ceil();
+    // more bins than values cannot come from finite data, only from overflow
+    (num_bins.is_finite() && num_bins <= F::from_usize(values.len())).then(|| num_bins.as_usize())
 }
 
 #[cfg(test)]
@@ -145,7 +242,7 @@ mod tests {
 
     #[test]
     fn test_co_embed2_dist_tau_d_expfit_meandiff() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             co_embed2_dist_tau_d_expfit_meandiff(&numbers).unwrap(),
@@ -156,7 +253,7 @@ mod tests {
 
     #[test]
     fn test_co_f1ecac_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             co_f1ecac(&numbers).unwrap(),
@@ -167,14 +264,74 @@ mod tests {
 
     #[test]
     fn test_co_firstmin_ac_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(co_firstmin_ac(&numbers).unwrap(), 77.0, epsilon = 1e-12);
     }
 
+    #[test]
+    fn test_in_auto_mutual_info_stats_40_gaussian_fmmi_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            in_auto_mutual_info_stats_40_gaussian_fmmi(&numbers).unwrap(),
+            40.0,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_in_auto_mutual_info_stats_40_gaussian_fmmi_first_minimum_same_original() {
+        let numbers = load_test_data::<f64>();
+        let diff = numbers
+            .iter()
+            .tuple_windows()
+            .map(|(a, b)| b - a)
+            .collect_vec();
+        let every = |k| numbers.iter().copied().step_by(k).collect_vec();
+
+        // values of the reference implementation, whose first minimum is below 40
+        for (values, expected) in [
+            (diff, 38.0),
+            (every(2), 22.0),
+            (every(3), 14.0),
+            (every(4), 10.0),
+            (every(5), 8.0),
+            (numbers[..100].to_vec(), 14.0),
+        ] {
+            assert_eq!(
+                in_auto_mutual_info_stats_40_gaussian_fmmi(&values).unwrap(),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_co_autocorrs_same_as_direct() {
+        let numbers = load_test_data::<f64>();
+
+        for len in [2, 3, 17, 97, 128, 129, 200, numbers.len()] {
+            let values = &numbers[..len];
+            let mean = mean(values).unwrap();
+            let lagged = |tau: usize| {
+                values
+                    .iter()
+                    .zip(values.iter().skip(tau))
+                    .map(|(&a, &b)| (a - mean) * (b - mean))
+                    .sum::<f64>()
+            };
+
+            let ac = co_autocorrs(values).unwrap();
+            assert!(ac.len() > len);
+            for (tau, &a) in ac.iter().take(len + 1).enumerate() {
+                assert_abs_diff_eq!(a, lagged(tau) / lagged(0), epsilon = 1e-12);
+            }
+        }
+    }
+
     #[test]
     fn test_co_trev_1_num_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             co_trev_1_num(&numbers).unwrap(),
@@ -0,0 +1,80 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::hist_count::{joint_hist_counts_lagged, JointBinData};
+use crate::primitive::Float;
+use crate::utility::{any_nan, total_cmp};
+use itertools::Itertools;
+
+pub fn co_histogram_ami_even_2_5<F: Float>(values: &[F]) -> Catch22Result<F> {
+    co_histogram_ami_even_2_5_with_context(&SeriesContext::new(values))
+}
+
+pub fn co_histogram_ami_even_2_5_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    co_histogram_ami_even::<F, 5, 6>(ctx.values(), 2)
+}
+
+fn co_histogram_ami_even<F: Float, const B: usize, const BN: usize>(
+    values: &[F],
+    tau: usize,
+) -> Catch22Result<F> {
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+
+    let (min, max) = values
+        .iter()
+        .copied()
+        .minmax_by(total_cmp)
+        .into_option()
+        .ok_or(Catch22Error::EmptyInput)?;
+
+    // the bins are widened by 0.1 on both sides so that the extrema fall inside
+    let margin = F::from_f64(0.1);
+    let histgram: JointBinData<F, B, BN> =
+        joint_hist_counts_lagged::<F, B, BN>(values, tau, min - margin, max + margin)?;
+
+    let total = F::from_usize(histgram.count.iter().flatten().sum::<usize>());
+    let pij = histgram
+        .count
+        .map(|row| row.map(|c| F::from_usize(c) / total));
+
+    let mut pi = [F::zero(); B];
+    let mut pj = [F::zero(); B];
+    for (i, row) in pij.iter().enumerate() {
+        for (j, &p) in row.iter().enumerate() {
+            pi[i] += p;
+            pj[j] += p;
+        }
+    }
+
+    let mut ami = F::zero();
+    for (i, row) in pij.iter().enumerate() {
+        for (j, &p) in row.iter().enumerate() {
+            if p > F::zero() {
+                ami += p * (p / (pi[i] * pj[j])).ln();
+            }
+        }
+    }
+
+    Ok(ami)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::load_test_data;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_co_histogram_ami_even_2_5_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            co_histogram_ami_even_2_5(&numbers).unwrap(),
+            1.00638907799376,
+            epsilon = 1e-12
+        );
+    }
+}
@@ -1,42 +1,52 @@
+use crate::context::SeriesContext;
 use crate::error::Catch22Result;
 use crate::hist_count::{hist_counts, BinData};
 use crate::primitive::Float;
 
-pub fn dn_histogram_mode_5(y: &[Float]) -> Catch22Result<Float> {
-    dn_histogram_mode::<5, 6>(y)
+pub fn dn_histogram_mode_5<F: Float>(y: &[F]) -> Catch22Result<F> {
+    dn_histogram_mode_5_with_context(&SeriesContext::new(y))
 }
 
-pub fn dn_histogram_mode_10(y: &[Float]) -> Catch22Result<Float> {
-    dn_histogram_mode::<10, 11>(y)
+pub fn dn_histogram_mode_5_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    dn_histogram_mode::<F, 5, 6>(ctx.values())
 }
 
-fn dn_histogram_mode<const B: usize, const BN: usize>(y: &[Float]) -> Catch22Result<Float> {
+pub fn dn_histogram_mode_10<F: Float>(y: &[F]) -> Catch22Result<F> {
+    dn_histogram_mode_10_with_context(&SeriesContext::new(y))
+}
+
+pub fn dn_histogram_mode_10_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    dn_histogram_mode::<F, 10, 11>(ctx.values())
+}
+
+fn dn_histogram_mode<F: Float, const B: usize, const BN: usize>(y: &[F]) -> Catch22Result<F> {
     // NaN check
     if y.iter().any(|&x| x.is_nan()) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let histgram: BinData<B, BN> = hist_counts::<B, BN>(y)?;
+    let histgram: BinData<F, B, BN> = hist_counts::<F, B, BN>(y)?;
 
     let mut max_count = 0usize;
     let mut num_maxs = 1usize;
-    let mut out = 0.0;
+    let half = F::from_f64(0.5);
+    let mut out = F::zero();
     for (i, &count) in histgram.count.iter().enumerate() {
         match count.cmp(&max_count) {
             std::cmp::Ordering::Greater => {
                 max_count = count;
                 num_maxs = 1;
-                out = (histgram.edges[i] + histgram.edges[i + 1]) * 0.5;
+                out = (histgram.edges[i] + histgram.edges[i + 1]) * half;
             }
             std::cmp::Ordering::Equal => {
                 num_maxs += 1;
-                out += (histgram.edges[i] + histgram.edges[i + 1]) * 0.5;
+                out += (histgram.edges[i] + histgram.edges[i + 1]) * half;
             }
             std::cmp::Ordering::Less => {}
         }
     }
 
-    Ok(out / num_maxs as Float)
+    Ok(out / F::from_usize(num_maxs))
 }
 
 #[cfg(test)]
@@ -47,7 +57,7 @@ mod tests {
 
     #[test]
     fn test_dn_histogram_mode_5() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             dn_histogram_mode_5(&numbers).unwrap(),
@@ -58,7 +68,7 @@ mod tests {
 
     #[test]
     fn test_dn_histogram_mode_10() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             dn_histogram_mode_10(&numbers).unwrap(),
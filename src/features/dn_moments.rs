@@ -0,0 +1,31 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::utility::any_nan;
+
+pub fn dn_mean<F: Float>(values: &[F]) -> Catch22Result<F> {
+    dn_mean_with_context(&SeriesContext::new(values))
+}
+
+pub fn dn_mean_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    if any_nan(ctx.values()) {
+        return Ok(F::nan());
+    }
+
+    ctx.mean().ok_or(Catch22Error::EmptyInput)
+}
+
+pub fn dn_spread_std<F: Float>(values: &[F]) -> Catch22Result<F> {
+    dn_spread_std_with_context(&SeriesContext::new(values))
+}
+
+pub fn dn_spread_std_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    if any_nan(ctx.values()) {
+        return Ok(F::nan());
+    }
+
+    ctx.stddev().ok_or(Catch22Error::SizeUnder {
+        len: ctx.values().len(),
+        expect_minimum: 2,
+    })
+}
@@ -1,87 +1,289 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
-use crate::stat::median;
-use itertools::Itertools;
+use crate::utility::total_cmp;
 use num::signum;
-use unwrap_ord::UnwrapOrd;
+use std::cmp::Reverse;
 
-pub fn dn_outliner_include_p_001_mdrmd(y: &[Float]) -> Catch22Result<Float> {
-    dn_outliner_include_np_001_mdrmd(y, 1)
+pub fn dn_outliner_include_p_001_mdrmd<F: Float>(y: &[F]) -> Catch22Result<F> {
+    dn_outliner_include_p_001_mdrmd_with_context(&SeriesContext::new(y))
 }
 
-pub fn dn_outliner_include_n_001_mdrmd(y: &[Float]) -> Catch22Result<Float> {
-    dn_outliner_include_np_001_mdrmd(y, -1)
+pub fn dn_outliner_include_p_001_mdrmd_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    dn_outliner_include_np_001_mdrmd(ctx, 1)
+}
+
+pub fn dn_outliner_include_n_001_mdrmd<F: Float>(y: &[F]) -> Catch22Result<F> {
+    dn_outliner_include_n_001_mdrmd_with_context(&SeriesContext::new(y))
+}
+
+pub fn dn_outliner_include_n_001_mdrmd_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    dn_outliner_include_np_001_mdrmd(ctx, -1)
 }
 
 const STEP: usize = 100;
 
-fn inc() -> f64 {
-    1.0 / STEP as f64
+fn inc<F: Float>() -> F {
+    F::one() / F::from_usize(STEP)
+}
+
+/// First threshold index `j` in `0..=threshold` for which `x > j * inc` no longer holds.
+fn first_threshold_not_exceeded<F: Float>(x: F, threshold: usize) -> usize {
+    let (mut low, mut high) = (0, threshold);
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if x > F::from_usize(mid) * inc() {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Fenwick tree over the indices `0..capacity` recording which of them are in the set.
+struct IndexSet<'a> {
+    tree: &'a mut Vec<usize>,
+    len: usize,
+}
+
+impl<'a> IndexSet<'a> {
+    /// An empty set, stored in `tree`.
+    fn new(tree: &'a mut Vec<usize>, capacity: usize) -> Self {
+        tree.clear();
+        tree.resize(capacity + 1, 0);
+        Self { tree, len: 0 }
+    }
+
+    fn insert(&mut self, index: usize) {
+        let mut i = index + 1;
+        while i < self.tree.len() {
+            self.tree[i] += 1;
+            i += i & i.wrapping_neg();
+        }
+        self.len += 1;
+    }
+
+    /// The `k`-th smallest index of the set, counting from 0. `k` must be less than `len`.
+    fn nth(&self, mut k: usize) -> usize {
+        let mut position = 0;
+        let mut step = self.tree.len().next_power_of_two();
+        while step > 0 {
+            let next = position + step;
+            if next < self.tree.len() && self.tree[next] <= k {
+                k -= self.tree[next];
+                position = next;
+            }
+            step /= 2;
+        }
+        position
+    }
+
+    /// Median of the 1-based indices of the set, averaging the middle two of an even count.
+    fn median<F: Float>(&self) -> F {
+        let value = |k| F::from_usize(self.nth(k) + 1);
+        let mid = self.len / 2;
+        if self.len % 2 == 1 {
+            value(mid)
+        } else {
+            (value(mid - 1) + value(mid)) / F::from_f64(2.0)
+        }
+    }
+}
+
+/// Median of the list in which each value is repeated by its weight, averaging the middle
+/// two of an even count and sorting the value indices into `order`.
+fn weighted_median<F: Float>(values: &[F], weights: &[usize], order: &mut Vec<usize>) -> F {
+    order.clear();
+    order.extend(0..values.len());
+    order.sort_unstable_by(|&a, &b| total_cmp(&values[a], &values[b]));
+    let total = weights.iter().sum::<usize>();
+    if total == 0 {
+        return F::nan();
+    }
+    let value = |k: usize| {
+        let mut seen = 0;
+        order
+            .iter()
+            .find(|&&i| {
+                seen += weights[i];
+                k < seen
+            })
+            .map(|&i| values[i])
+            .unwrap()
+    };
+    let mid = total / 2;
+    if total % 2 == 1 {
+        value(mid)
+    } else {
+        (value(mid - 1) + value(mid)) / F::from_f64(2.0)
+    }
 }
 
-fn dn_outliner_include_np_001_mdrmd(y: &[Float], sign: i8) -> Catch22Result<Float> {
+fn dn_outliner_include_np_001_mdrmd<F: Float>(
+    ctx: &SeriesContext<F>,
+    sign: i8,
+) -> Catch22Result<F> {
+    let y = ctx.values();
     // NaN check
     if y.iter().any(|&x| x.is_nan()) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
     let y_first = *y.first().ok_or(Catch22Error::EmptyInput)?;
     if y.iter().skip(1).all(|&x| x == y_first) {
-        return Ok(0.0);
+        return Ok(F::zero());
     }
 
-    let sign = signum(sign);
+    let sign = F::from_f64(signum(sign) as f64);
 
-    let y_work = y.iter().map(|&x| x * sign as f64).collect_vec();
-    let tot = y_work.iter().filter(|&&x| x >= 0.0).count() as f64;
+    let mut y_work = ctx.floats();
+    y_work.extend(y.iter().map(|&x| x * sign));
+    let tot = F::from_usize(y_work.iter().filter(|&&x| x >= F::zero()).count());
 
-    let max = *y_work.iter().max_by_key(|&&x| UnwrapOrd(x)).unwrap();
+    let max = *y_work.iter().max_by(|a, b| total_cmp(*a, *b)).unwrap();
 
-    if max < 0.01 {
-        return Ok(0.0);
+    if max < F::from_f64(0.01) {
+        return Ok(F::zero());
     }
 
-    let threshold = ((max * STEP as f64) + 1.0) as usize;
+    let threshold = (max * F::from_usize(STEP)) + F::one();
+    if !threshold.is_finite() {
+        return Ok(F::nan());
+    }
+    let threshold = threshold.as_usize();
 
-    let mut fbi = None; // 一番最初に見つけたhigh_value_count == 0のときのj
-    let mut mj = 0; // high_value_count - 1が2を超えた最初のj
-    let mut ms_dti4 = Vec::with_capacity(threshold);
-    for j in 0..threshold {
-        let indices = y_work
-            .iter()
-            .enumerate()
-            .filter_map(|(idx, &x)| (x > j as f64 * inc()).then_some((idx + 1) as f64))
-            .collect_vec();
-        let high_value_count = indices.len();
-        let _indices_diff = indices
+    // y_work[i] exceeds the thresholds j < ends[i], so the set of exceeding indices shrinks
+    // as j grows and only changes at the distinct ends. Sweeping j downwards, every index is
+    // inserted once, and each run of thresholds sharing a set is handled at once.
+    let mut ends = ctx.indices();
+    ends.extend(
+        y_work
             .iter()
-            .zip(indices.iter().skip(1))
-            .map(|(&x, &y)| y - x)
-            .collect_vec();
+            .map(|&x| first_threshold_not_exceeded(x, threshold)),
+    );
+    let mut order = ctx.indices();
+    order.extend((0..y.len()).filter(|&i| ends[i] > 0));
+    order.sort_unstable_by_key(|&i| Reverse(ends[i]));
 
-        if high_value_count == 0 && fbi.is_none() {
-            fbi = Some(j);
-        }
-        if (high_value_count - 1) as f64 * (STEP as f64) / tot > 2.0 {
-            mj = j;
+    // The thresholds are kept up to mj, the last one at which the high value count is
+    // above 2% of tot (or the first one if there is none). As the count only grows during
+    // the sweep, the runs kept are the last ones swept.
+    let mut tree = ctx.indices();
+    let mut indices = IndexSet::new(&mut tree, y.len());
+    let mut ms_dti4 = ctx.floats();
+    let mut weights = ctx.indices(); // number of thresholds of each value of ms_dti4
+    let mut ms = F::nan();
+    for (k, &i) in order.iter().enumerate() {
+        indices.insert(i);
+        let start = match order.get(k + 1) {
+            Some(&next) if ends[next] == ends[i] => continue,
+            Some(&next) => ends[next],
+            None => 0,
+        };
+
+        ms = indices.median::<F>() / (F::from_usize(y.len()) / F::from_f64(2.0)) - F::one();
+        if (F::from_usize(indices.len) - F::one()) * F::from_usize(STEP) / tot > F::from_f64(2.0) {
+            ms_dti4.push(ms);
+            weights.push(ends[i] - start);
         }
-        ms_dti4.push(median(&indices) / (y.len() as f64 / 2.0) - 1.0);
     }
-    let fbi = fbi.unwrap_or(threshold - 1);
 
-    let trim_limit = mj.min(fbi);
-    Ok(median(&ms_dti4[0..trim_limit + 1]))
+    if ms_dti4.is_empty() {
+        // only the first threshold is kept
+        return Ok(ms);
+    }
+    Ok(weighted_median(&ms_dti4, &weights, &mut order))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utility::load_test_data;
+    use crate::test_utility::{arbitrary_series, load_test_data};
+    use crate::utility::total_cmp;
     use approx::assert_abs_diff_eq;
+    use itertools::Itertools;
+    use proptest::prelude::*;
+
+    /// Median of `y`, NaN if it is empty.
+    fn median<F: Float>(y: &[F]) -> F {
+        if y.is_empty() {
+            return F::nan();
+        }
+        let mut y = y.to_vec();
+        y.sort_by(total_cmp);
+        let mid = y.len() / 2;
+        if y.len() % 2 == 1 {
+            y[mid]
+        } else {
+            (y[mid - 1] + y[mid]) / F::from_f64(2.0)
+        }
+    }
+
+    /// The threshold by threshold scan of the reference implementation.
+    fn scan<F: Float>(y: &[F], sign: i8) -> F {
+        if y.iter().any(|&x| x.is_nan()) {
+            return F::nan();
+        }
+        if y.iter().all(|&x| x == y[0]) {
+            return F::zero();
+        }
+        let sign = F::from_f64(signum(sign) as f64);
+        let y_work = y.iter().map(|&x| x * sign).collect_vec();
+        let tot = F::from_usize(y_work.iter().filter(|&&x| x >= F::zero()).count());
+        let max = *y_work.iter().max_by(|a, b| total_cmp(*a, *b)).unwrap();
+        if max < F::from_f64(0.01) {
+            return F::zero();
+        }
+        let threshold = ((max * F::from_usize(STEP)) + F::one()).as_usize();
+
+        let mut fbi = None;
+        let mut mj = 0;
+        let mut ms_dti4 = vec![];
+        for j in 0..threshold {
+            let indices = y_work
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, &x)| {
+                    (x > F::from_usize(j) * inc()).then_some(F::from_usize(idx + 1))
+                })
+                .collect_vec();
+            if indices.is_empty() && fbi.is_none() {
+                fbi = Some(j);
+            }
+            if (F::from_usize(indices.len()) - F::one()) * F::from_usize(STEP) / tot
+                > F::from_f64(2.0)
+            {
+                mj = j;
+            }
+            ms_dti4.push(median(&indices) / (F::from_usize(y.len()) / F::from_f64(2.0)) - F::one());
+        }
+        let trim_limit = mj.min(fbi.unwrap_or(threshold - 1));
+        median(&ms_dti4[0..trim_limit + 1])
+    }
+
+    proptest! {
+        #[test]
+        fn test_dn_outliner_include_same_as_scan(values in arbitrary_series()) {
+            let values = values.into_iter().filter(|x| x.abs() < 1e2).collect_vec();
+            for sign in [1, -1] {
+                let expected = scan(&values, sign);
+                match dn_outliner_include_np_001_mdrmd(&SeriesContext::new(&values), sign) {
+                    Ok(actual) if expected.is_nan() => prop_assert!(actual.is_nan()),
+                    Ok(actual) => prop_assert_eq!(actual, expected),
+                    Err(_) => prop_assert!(values.is_empty()),
+                }
+            }
+        }
+    }
 
     #[test]
     fn test_dn_outliner_include_n_001_mdrmd_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             dn_outliner_include_n_001_mdrmd(&numbers).unwrap(),
@@ -92,7 +294,7 @@ mod tests {
 
     #[test]
     fn test_dn_outliner_include_p_001_mdrmd_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             dn_outliner_include_p_001_mdrmd(&numbers).unwrap(),
@@ -1,40 +1,94 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
-use crate::features::co_auto_corr::co_firstzero;
+use crate::features::co_auto_corr::first_zero;
 use crate::primitive::Float;
-use crate::utility::{any_nan, mean, stddev};
-use itertools::Itertools;
+use crate::utility::{any_nan, stddev};
 
-pub fn fc_local_simple_mean1_tauresrat(values: &[Float]) -> Catch22Result<Float> {
-    fc_local_simple_mean_tauresrat(values, 1)
+pub fn fc_local_simple_mean1_tauresrat<F: Float>(values: &[F]) -> Catch22Result<F> {
+    fc_local_simple_mean1_tauresrat_with_context(&SeriesContext::new(values))
 }
 
-pub fn fc_localsimple_mean3_stderr(values: &[Float]) -> Catch22Result<Float> {
-    fc_local_simple_mean_stderr(values, 3)
+pub fn fc_local_simple_mean1_tauresrat_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    fc_local_simple_mean_tauresrat(ctx, 1)
 }
 
-fn fc_local_simple_mean_tauresrat(values: &[Float], train_length: usize) -> Catch22Result<Float> {
+pub fn fc_localsimple_mean3_stderr<F: Float>(values: &[F]) -> Catch22Result<F> {
+    fc_localsimple_mean3_stderr_with_context(&SeriesContext::new(values))
+}
+
+pub fn fc_localsimple_mean3_stderr_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    fc_local_simple_mean_stderr(ctx, 3)
+}
+
+fn fc_local_simple_mean_tauresrat<F: Float>(
+    ctx: &SeriesContext<F>,
+    train_length: usize,
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let res = train_mean(values, train_length)?;
-    let res_ac_1st_z = co_firstzero(&res, values.len() - train_length)?;
-    let y_ac_1st_x = co_firstzero(values, values.len())?;
+    let mut res = ctx.floats();
+    fc_local_simple_mean_residuals_into(values, train_length, &mut res)?;
+    let mut res_ac = ctx.floats();
+    ctx.autocorrs_into(&res, &mut res_ac)?;
+    let res_ac_1st_z = first_zero(&res_ac, values.len() - train_length);
+    let y_ac_1st_x = ctx.first_zero()?;
 
-    Ok(res_ac_1st_z as Float / y_ac_1st_x as Float)
+    Ok(F::from_usize(res_ac_1st_z) / F::from_usize(y_ac_1st_x))
 }
 
-fn fc_local_simple_mean_stderr(values: &[Float], train_length: usize) -> Catch22Result<Float> {
+fn fc_local_simple_mean_stderr<F: Float>(
+    ctx: &SeriesContext<F>,
+    train_length: usize,
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let res = train_mean(values, train_length)?;
+    let mut res = ctx.floats();
+    fc_local_simple_mean_residuals_into(values, train_length, &mut res)?;
+
+    stddev(&res).ok_or(Catch22Error::SizeUnder {
+        len: values.len(),
+        expect_minimum: train_length.saturating_add(2),
+    })
+}
+
+/// Longest training window that [`fc_local_simple_mean_residuals`] averages directly: a
+/// rolling sum saves little on such short windows.
+pub const DIRECT_MEAN_MAX_LENGTH: usize = 16;
 
-    Ok(stddev(&res).unwrap())
+/// Residuals of the local simple forecaster, which predicts each value as the mean of the
+/// `train_length` values before it.
+///
+/// Windows of up to [`DIRECT_MEAN_MAX_LENGTH`] samples, which include those of the catch22
+/// features, are averaged directly, giving exactly `mean(&values[i..i + train_length])`.
+/// The sum of longer windows is updated as the window slides with compensated (Neumaier)
+/// additions, and summed afresh every `train_length` steps and whenever it is not finite,
+/// so that it agrees with the direct mean up to rounding, even next to very large or
+/// infinite values.
+pub fn fc_local_simple_mean_residuals<F: Float>(
+    values: &[F],
+    train_length: usize,
+) -> Catch22Result<Vec<F>> {
+    let mut residuals = vec![];
+    fc_local_simple_mean_residuals_into(values, train_length, &mut residuals)?;
+    Ok(residuals)
 }
 
-fn train_mean(values: &[Float], train_length: usize) -> Catch22Result<Vec<Float>> {
+/// Same as [`fc_local_simple_mean_residuals`], writing into `residuals`.
+pub(crate) fn fc_local_simple_mean_residuals_into<F: Float>(
+    values: &[F],
+    train_length: usize,
+    residuals: &mut Vec<F>,
+) -> Catch22Result<()> {
     if train_length == 0 {
         return Err(Catch22Error::InvalidUsizeParameter {
             name: "train_length",
@@ -42,25 +96,136 @@ fn train_mean(values: &[Float], train_length: usize) -> Catch22Result<Vec<Float>
         });
     }
 
-    Ok((0..values.len() - train_length)
-        .map(|i| {
-            let train_mean = mean(&values[i..i + train_length]).unwrap();
-            values[i + train_length] - train_mean
-        })
-        .collect_vec())
+    if values.len() <= train_length {
+        return Err(Catch22Error::SizeUnder {
+            len: values.len(),
+            expect_minimum: train_length.saturating_add(1),
+        });
+    }
+
+    residuals.clear();
+    if train_length <= DIRECT_MEAN_MAX_LENGTH {
+        residuals.extend(values.windows(train_length + 1).map(|w| {
+            let (train, next) = w.split_at(train_length);
+            next[0] - train.iter().copied().sum::<F>() / F::from_usize(train_length)
+        }));
+        return Ok(());
+    }
+
+    let mut sum = WindowSum::default();
+    residuals.extend((0..values.len() - train_length).map(|i| {
+        let window = &values[i..i + train_length];
+        if i % train_length == 0 {
+            sum = WindowSum::of(window);
+        } else {
+            sum.add(-values[i - 1]);
+            sum.add(values[i + train_length - 1]);
+            if !sum.value().is_finite() {
+                sum = WindowSum::of(window);
+            }
+        }
+        values[i + train_length] - sum.value() / F::from_usize(train_length)
+    }));
+    Ok(())
+}
+
+/// Neumaier sum, whose compensation keeps the low order digits that large terms round off.
+#[derive(Default)]
+struct WindowSum<F> {
+    sum: F,
+    compensation: F,
+}
+
+impl<F: Float> WindowSum<F> {
+    /// Compensated sum of `window`, or its plain sum if that is not finite, since the
+    /// compensation of infinite terms is NaN.
+    fn of(window: &[F]) -> Self {
+        let mut sum = Self::default();
+        window.iter().for_each(|&x| sum.add(x));
+        if sum.value().is_finite() {
+            sum
+        } else {
+            Self {
+                sum: window.iter().copied().sum(),
+                compensation: F::zero(),
+            }
+        }
+    }
+
+    fn add(&mut self, x: F) {
+        let t = self.sum + x;
+        if self.sum.abs() >= x.abs() {
+            self.compensation += (self.sum - t) + x;
+        } else {
+            self.compensation += (x - t) + self.sum;
+        }
+        self.sum = t;
+    }
+
+    fn value(&self) -> F {
+        self.sum + self.compensation
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_utility::load_test_data;
+    use crate::test_utility::{arbitrary_series, load_test_data};
+    use crate::utility::mean;
     use approx::assert_abs_diff_eq;
+    use proptest::prelude::*;
 
     // other test cases...
 
+    #[test]
+    fn test_fc_local_simple_mean_residuals_same_as_direct() {
+        let values = load_test_data::<f64>();
+
+        for train_length in [1, 2, 3, 7, 16, 17, 50] {
+            let residuals = fc_local_simple_mean_residuals(&values, train_length).unwrap();
+            assert_eq!(residuals.len(), values.len() - train_length);
+            for (i, &residual) in residuals.iter().enumerate() {
+                let expected =
+                    values[i + train_length] - mean(&values[i..i + train_length]).unwrap();
+                if train_length <= DIRECT_MEAN_MAX_LENGTH {
+                    assert_eq!(residual, expected);
+                } else {
+                    assert_abs_diff_eq!(residual, expected, epsilon = 1e-12);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_fc_local_simple_mean_residuals_next_to_large_values() {
+        let values = [1e20, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0];
+
+        let residuals = fc_local_simple_mean_residuals(&values, 3).unwrap();
+
+        assert_eq!(residuals[1..], [2.0, 2.0, 2.0, 2.0]);
+
+        let mut values = values;
+        values[0] = f64::INFINITY;
+        let residuals = fc_local_simple_mean_residuals(&values, 3).unwrap();
+
+        assert_eq!(residuals[0], f64::NEG_INFINITY);
+        assert_eq!(residuals[1..], [2.0, 2.0, 2.0, 2.0]);
+    }
+
+    #[test]
+    fn test_fc_local_simple_mean_residuals_huge_train_length() {
+        assert_eq!(
+            fc_local_simple_mean_residuals(&[1.0, 2.0], usize::MAX),
+            Err(Catch22Error::SizeUnder {
+                len: 2,
+                expect_minimum: usize::MAX
+            })
+        );
+    }
+
     #[test]
     fn test_fc_localsimple_mean1_tauresrat_same_original() {
-        let values = load_test_data();
+        let values = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             fc_local_simple_mean1_tauresrat(&values).unwrap(),
@@ -71,7 +236,7 @@ mod tests {
 
     #[test]
     fn test_fc_localsimple_mean3_stderr_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             fc_localsimple_mean3_stderr(&numbers).unwrap(),
@@ -79,4 +244,57 @@ mod tests {
             epsilon = 1e-7
         );
     }
+
+    proptest! {
+        #[test]
+        fn test_fc_local_simple_mean_residuals_never_panic(
+            values in arbitrary_series(),
+            train_length in prop_oneof![0..6usize, Just(usize::MAX), any::<usize>()],
+        ) {
+            let _ = fc_local_simple_mean_residuals(&values, train_length);
+        }
+
+        #[test]
+        fn test_fc_local_simple_mean_residuals_same_as_direct_mean(
+            values in prop::collection::vec(
+                prop_oneof![
+                    -10.0..10.0f64,
+                    -1e20..1e20f64,
+                    Just(f64::INFINITY),
+                    Just(f64::NEG_INFINITY),
+                    Just(f64::NAN),
+                ],
+                30..80,
+            ),
+            train_length in 1..30usize,
+        ) {
+            let residuals = fc_local_simple_mean_residuals(&values, train_length).unwrap();
+
+            for (i, &residual) in residuals.iter().enumerate() {
+                let window = &values[i..i + train_length];
+                let expected = values[i + train_length] - mean(window).unwrap();
+                if train_length <= DIRECT_MEAN_MAX_LENGTH {
+                    prop_assert!(
+                        residual.to_bits() == expected.to_bits()
+                            || (residual.is_nan() && expected.is_nan()),
+                        "{} != {} at {}", residual, expected, i
+                    );
+                } else if expected.is_finite() {
+                    // the direct sum itself is only exact to a few ulps of its largest term
+                    let scale = window
+                        .iter()
+                        .chain([&values[i + train_length]])
+                        .fold(1.0f64, |m, x| m.max(x.abs()));
+                    prop_assert!(
+                        (residual - expected).abs() <= 1e-12 * scale,
+                        "{} != {} at {}", residual, expected, i
+                    );
+                } else if expected.is_nan() {
+                    prop_assert!(residual.is_nan());
+                } else {
+                    prop_assert_eq!(residual, expected);
+                }
+            }
+        }
+    }
 }
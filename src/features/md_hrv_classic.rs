@@ -0,0 +1,49 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::utility::{any_nan, mean_iter};
+
+pub fn md_hrv_classic_pnn40<F: Float>(values: &[F]) -> Catch22Result<F> {
+    md_hrv_classic_pnn40_with_context(&SeriesContext::new(values))
+}
+
+pub fn md_hrv_classic_pnn40_with_context<F: Float>(ctx: &SeriesContext<F>) -> Catch22Result<F> {
+    md_hrv_classic_pnn(ctx.values(), F::from_f64(40.0))
+}
+
+fn md_hrv_classic_pnn<F: Float>(values: &[F], pnn_x: F) -> Catch22Result<F> {
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+
+    // successive differences are in seconds, the threshold in milliseconds
+    mean_iter(values.iter().zip(values.iter().skip(1)).map(|(&a, &an)| {
+        if (an - a).abs() * F::from_f64(1000.0) > pnn_x {
+            F::one()
+        } else {
+            F::zero()
+        }
+    }))
+    .ok_or(Catch22Error::SizeUnder {
+        len: values.len(),
+        expect_minimum: 2,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::load_test_data;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_md_hrv_classic_pnn40_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            md_hrv_classic_pnn40(&numbers).unwrap(),
+            0.31970260223048,
+            epsilon = 1e-12
+        );
+    }
+}
@@ -0,0 +1,100 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::spline::{spline_evaluate, spline_fit_into};
+use crate::stat::covariance;
+use crate::utility::any_nan;
+use itertools::Itertools;
+
+pub fn pd_periodicity_wang_th0_01<F: Float>(values: &[F]) -> Catch22Result<F> {
+    pd_periodicity_wang_th0_01_with_context(&SeriesContext::new(values))
+}
+
+pub fn pd_periodicity_wang_th0_01_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    pd_periodicity_wang(ctx, F::from_f64(0.01))
+}
+
+fn pd_periodicity_wang<F: Float>(ctx: &SeriesContext<F>, threshold: F) -> Catch22Result<F> {
+    let values = ctx.values();
+    if values.len() < 5 {
+        return Err(Catch22Error::SizeUnder {
+            len: values.len(),
+            expect_minimum: 5,
+        });
+    }
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+
+    let size = values.len();
+
+    // detrend with a cubic spline of two pieces
+    let mut x = ctx.floats();
+    x.extend((0..size).map(F::from_usize));
+    let breaks = [
+        F::zero(),
+        F::from_usize(size / 2 - 1),
+        F::from_usize(size - 1),
+    ];
+    let (mut knots, mut coefs) = (ctx.floats(), ctx.floats());
+    let (origin, scale) = spline_fit_into(
+        &x,
+        values,
+        &breaks,
+        &mut knots,
+        &mut coefs,
+        &mut ctx.floats(),
+        &mut ctx.floats(),
+    )?;
+    let mut y_sub = ctx.floats();
+    y_sub.extend(
+        x.iter()
+            .zip(values.iter())
+            .map(|(&x, &y)| y - spline_evaluate(origin, scale, &knots, &coefs, x)),
+    );
+
+    // autocovariances up to 1/3 of the length of the series
+    let ac_max = size.div_ceil(3);
+    let mut acf = ctx.floats();
+    acf.extend((1..=ac_max).map(|tau| covariance(&y_sub[..size - tau], &y_sub[tau..])));
+
+    // the first peak that has a trough before it, rises at least `threshold` above
+    // the last such trough and corresponds to a positive correlation
+    let mut trough = None;
+    for (i, (&prev, &a, &next)) in acf.iter().tuple_windows().enumerate() {
+        let slope_in = a - prev;
+        let slope_out = next - a;
+        if slope_in < F::zero() && slope_out > F::zero() {
+            trough = Some(i + 1);
+        } else if slope_in > F::zero() && slope_out < F::zero() {
+            let peak = i + 1;
+            if let Some(trough) = trough {
+                if acf[peak] - acf[trough] >= threshold && acf[peak] >= F::zero() {
+                    return Ok(F::from_usize(peak));
+                }
+            }
+        }
+    }
+
+    Ok(F::zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::load_test_data;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_pd_periodicity_wang_th0_01_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            pd_periodicity_wang_th0_01(&numbers).unwrap(),
+            0.0,
+            epsilon = 1e-12
+        );
+    }
+}
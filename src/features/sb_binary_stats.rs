@@ -1,8 +1,16 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
-use crate::utility::{any_nan, mean_iter};
+use crate::utility::any_nan;
 
-pub fn sb_binarystats_diff_longstretch0(values: &[Float]) -> Catch22Result<Float> {
+pub fn sb_binarystats_diff_longstretch0<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sb_binarystats_diff_longstretch0_with_context(&SeriesContext::new(values))
+}
+
+pub fn sb_binarystats_diff_longstretch0_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if values.is_empty() {
         return Err(Catch22Error::EmptyInput);
     }
@@ -13,13 +21,13 @@ pub fn sb_binarystats_diff_longstretch0(values: &[Float]) -> Catch22Result<Float
         });
     }
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
     let (max_strech0, _) = values
         .iter()
         .zip(values.iter().skip(1))
-        .map(|(v, nv)| if (nv - v).is_sign_negative() { 0 } else { 1 })
+        .map(|(&v, &nv)| if (nv - v).is_sign_negative() { 0 } else { 1 })
         .enumerate()
         .fold((0, 0), |(max_strech0, last1), (i, v)| {
             if v == 1 || i == (values.len() - 2) {
@@ -34,10 +42,17 @@ pub fn sb_binarystats_diff_longstretch0(values: &[Float]) -> Catch22Result<Float
             }
         });
 
-    Ok(max_strech0 as Float)
+    Ok(F::from_f64(max_strech0 as f64))
+}
+
+pub fn sb_binarystats_mean_longstretch1<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sb_binarystats_mean_longstretch1_with_context(&SeriesContext::new(values))
 }
 
-pub fn sb_binarystats_mean_longstretch1(values: &[Float]) -> Catch22Result<Float> {
+pub fn sb_binarystats_mean_longstretch1_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if values.is_empty() {
         return Err(Catch22Error::EmptyInput);
     }
@@ -48,10 +63,10 @@ pub fn sb_binarystats_mean_longstretch1(values: &[Float]) -> Catch22Result<Float
         });
     }
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
-    let mean = mean_iter(values.iter().copied()).unwrap();
+    let mean = ctx.mean().unwrap();
 
     let (max_strech1, _) = values
         .iter()
@@ -71,7 +86,7 @@ pub fn sb_binarystats_mean_longstretch1(values: &[Float]) -> Catch22Result<Float
             }
         });
 
-    Ok(max_strech1 as Float)
+    Ok(F::from_f64(max_strech1 as f64))
 }
 
 #[cfg(test)]
@@ -84,7 +99,7 @@ mod tests {
 
     #[test]
     fn test_sb_binarystats_diff_longstretch0_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             sb_binarystats_diff_longstretch0(&numbers).unwrap(),
@@ -95,7 +110,7 @@ mod tests {
 
     #[test]
     fn test_sb_binarystats_mean_longstretch1_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             sb_binarystats_mean_longstretch1(&numbers).unwrap(),
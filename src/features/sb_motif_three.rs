@@ -1,42 +1,45 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
-use crate::utility::{any_nan, entropy, sb_coarsegrain, Alphabet};
+use crate::utility::{any_nan, entropy, sb_coarsegrain_into, Quantiles};
+use itertools::Itertools;
 
-pub fn sb_motif_three_quantile_hh(values: &[Float]) -> Catch22Result<Float> {
+pub fn sb_motif_three_quantile_hh<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sb_motif_three_quantile_hh_with_context(&SeriesContext::new(values))
+}
+
+pub fn sb_motif_three_quantile_hh_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if values.is_empty() {
         return Err(Catch22Error::EmptyInput);
     }
 
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
     const ALPHABET_SIZE: usize = 3;
-    let labels = sb_coarsegrain(values, ALPHABET_SIZE);
-
-    let mut r1: [Vec<usize>; ALPHABET_SIZE] = Default::default();
-    for (i, r) in r1.iter_mut().enumerate() {
-        for (j, &l) in labels.iter().enumerate() {
-            if l == Alphabet::from_zero_indexed(i) {
-                r.push(j);
-            }
-        }
+    let mut labels = ctx.indices();
+    sb_coarsegrain_into(
+        values,
+        &Quantiles::from_sorted(ctx.sorted()),
+        ALPHABET_SIZE,
+        &mut ctx.floats(),
+        &mut ctx.indices(),
+        &mut labels,
+    )?;
 
-        if !r.is_empty() && *r.last().unwrap() == values.len() - 1 {
-            r.pop();
-        }
+    // transitions between consecutive labels
+    let mut counts = [[0usize; ALPHABET_SIZE]; ALPHABET_SIZE];
+    for (&from, &to) in labels.iter().tuple_windows() {
+        counts[from][to] += 1;
     }
 
-    let mut hh = 0.0;
-    for r in &mut r1 {
-        let mut v = [0.0; ALPHABET_SIZE];
-        for (j, vj) in v.iter_mut().enumerate() {
-            let s = r
-                .iter()
-                .filter(|&&r| labels[r + 1] == Alphabet::from_zero_indexed(j))
-                .count();
-            *vj = s as Float / (values.len() as Float - 1.0);
-        }
+    let mut hh = F::zero();
+    for row in &counts {
+        let v = row.map(|s| F::from_usize(s) / F::from_usize(values.len() - 1));
         hh += entropy(&v);
     }
 
@@ -53,7 +56,7 @@ mod tests {
 
     #[test]
     fn test_sb_motif_three_quantile_hh_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             sb_motif_three_quantile_hh(&numbers).unwrap(),
@@ -0,0 +1,83 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::utility::{any_nan, mean, sb_coarsegrain_into, total_cmp, Quantiles};
+use itertools::Itertools;
+
+pub fn sb_transition_matrix_3ac_sumdiagcov<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sb_transition_matrix_3ac_sumdiagcov_with_context(&SeriesContext::new(values))
+}
+
+pub fn sb_transition_matrix_3ac_sumdiagcov_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    let values = ctx.values();
+    let first = *values.first().ok_or(Catch22Error::EmptyInput)?;
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+    if values.iter().all(|&x| x == first) {
+        return Ok(F::nan());
+    }
+
+    const NUM_GROUPS: usize = 3;
+
+    let tau = ctx.first_zero()?;
+    if tau == 0 {
+        // the autocorrelation is undefined, e.g. because the variance overflows
+        return Ok(F::nan());
+    }
+    let mut y_down = ctx.floats();
+    y_down.extend(values.iter().copied().step_by(tau));
+    let mut sorted = ctx.floats();
+    sorted.extend_from_slice(&y_down);
+    sorted.sort_unstable_by(total_cmp);
+
+    let mut labels = ctx.indices();
+    sb_coarsegrain_into(
+        &y_down,
+        &Quantiles::from_sorted(&sorted),
+        NUM_GROUPS,
+        &mut ctx.floats(),
+        &mut ctx.indices(),
+        &mut labels,
+    )?;
+
+    let mut t = [[F::zero(); NUM_GROUPS]; NUM_GROUPS];
+    for (&from, &to) in labels.iter().tuple_windows() {
+        t[from][to] += F::one();
+    }
+    let num_transitions = F::from_usize(y_down.len() - 1);
+    for row in t.iter_mut() {
+        for x in row.iter_mut() {
+            *x /= num_transitions;
+        }
+    }
+
+    // sum of the diagonal of the covariance between the columns of the transition matrix
+    Ok((0..NUM_GROUPS)
+        .map(|j| {
+            let column = t.map(|row| row[j]);
+            let m = mean(&column).unwrap();
+            column.iter().map(|&x| (x - m).powi(2)).sum::<F>() / F::from_usize(NUM_GROUPS - 1)
+        })
+        .sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::load_test_data;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_sb_transition_matrix_3ac_sumdiagcov_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            sb_transition_matrix_3ac_sumdiagcov(&numbers).unwrap(),
+            0.08,
+            epsilon = 1e-12
+        );
+    }
+}
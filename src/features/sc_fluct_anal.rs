@@ -1,127 +1,132 @@
+use crate::context::SeriesContext;
 use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
 use crate::stat::{linear_regression, linear_regression_square_error};
-use crate::utility::any_nan;
+use crate::utility::{any_nan, total_cmp};
 use itertools::Itertools;
-use unwrap_ord::UnwrapOrd;
 
 enum Method {
     Dfa,
     Rsrangefit,
 }
 
-pub fn sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1(values: &[Float]) -> Catch22Result<Float> {
-    sc_fluct_anal_2_50_1_logi_prop_r1(values, 2, Method::Dfa)
+pub fn sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1_with_context(&SeriesContext::new(values))
 }
 
-pub fn sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1(values: &[Float]) -> Catch22Result<Float> {
-    sc_fluct_anal_2_50_1_logi_prop_r1(values, 1, Method::Rsrangefit)
+pub fn sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    sc_fluct_anal_2_50_1_logi_prop_r1(ctx, 2, Method::Dfa)
 }
 
-fn sc_fluct_anal_2_50_1_logi_prop_r1(
-    values: &[Float],
+pub fn sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1_with_context(&SeriesContext::new(values))
+}
+
+pub fn sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    sc_fluct_anal_2_50_1_logi_prop_r1(ctx, 1, Method::Rsrangefit)
+}
+
+fn sc_fluct_anal_2_50_1_logi_prop_r1<F: Float>(
+    ctx: &SeriesContext<F>,
     lag: usize,
     how: Method,
-) -> Catch22Result<Float> {
+) -> Catch22Result<F> {
+    let values = ctx.values();
     if values.is_empty() {
         return Err(Catch22Error::EmptyInput);
     }
     if any_nan(values) {
-        return Ok(Float::NAN);
+        return Ok(F::nan());
     }
 
+    // the time scales are integers, so they are always computed in double precision
     let lin_low = 5.0_f64.ln();
-    let lin_high = ((values.len() / 2) as Float).ln();
+    let lin_high = ((values.len() / 2) as f64).ln();
 
     const N_TAU_STEPS: usize = 50;
-    let tau_step = (lin_high - lin_low) / ((N_TAU_STEPS - 1) as Float);
-
-    let tau = (0..N_TAU_STEPS)
-        .map(|i| (lin_low + (i as Float) * tau_step).exp().round() as usize)
-        .dedup()
-        .collect_vec();
+    let tau_step = (lin_high - lin_low) / ((N_TAU_STEPS - 1) as f64);
+
+    let mut tau = [0usize; N_TAU_STEPS];
+    let mut n_tau = 0;
+    for t in (0..N_TAU_STEPS).map(|i| (lin_low + (i as f64) * tau_step).exp().round() as usize) {
+        if n_tau == 0 || tau[n_tau - 1] != t {
+            tau[n_tau] = t;
+            n_tau += 1;
+        }
+    }
+    let tau = &tau[..n_tau];
 
     if tau.len() < 12 {
-        return Ok(0.0);
+        return Ok(F::zero());
     }
 
-    let value_cumsum = values
-        .iter()
-        .step_by(lag)
-        .scan(0.0, |sum, v| {
-            *sum += v;
-            Some(*sum)
-        })
-        .collect_vec();
-
-    let x_reg = (1..=(*tau.last().unwrap()))
-        .map(|x| x as Float)
-        .collect_vec();
-
-    let fs = tau
-        .iter()
-        .map(|&t| {
-            let n_buffer = value_cumsum.len() / t;
-
-            let f = (0..n_buffer)
-                .map(|j| {
-                    let (a, b) = linear_regression(
-                        x_reg.iter().take(t),
-                        value_cumsum.iter().skip(j * t).take(t),
-                    );
-
-                    let vs = value_cumsum
-                        .iter()
-                        .skip(j * t)
-                        .take(t)
-                        .enumerate()
-                        .map(|(k, c)| c - (a * (k + 1) as Float + b));
-
-                    match how {
-                        Method::Rsrangefit => {
-                            let (min, max) = vs.minmax().into_option().unwrap();
-                            (max - min).powi(2)
-                        }
-                        Method::Dfa => vs.map(|v| v.powi(2)).sum::<Float>(),
+    let mut value_cumsum = ctx.floats();
+    value_cumsum.extend(values.iter().step_by(lag).scan(F::zero(), |sum, &v| {
+        *sum += v;
+        Some(*sum)
+    }));
+
+    let mut x_reg = ctx.floats();
+    x_reg.extend((1..=(*tau.last().unwrap())).map(F::from_usize));
+
+    let mut fs = [F::zero(); N_TAU_STEPS];
+    for (f_out, &t) in fs.iter_mut().zip(tau) {
+        let n_buffer = value_cumsum.len() / t;
+
+        let f = (0..n_buffer)
+            .map(|j| {
+                let (a, b) = linear_regression(
+                    x_reg.iter().take(t),
+                    value_cumsum.iter().skip(j * t).take(t),
+                );
+
+                let vs = value_cumsum
+                    .iter()
+                    .skip(j * t)
+                    .take(t)
+                    .enumerate()
+                    .map(|(k, &c)| c - (a * F::from_usize(k + 1) + b));
+
+                match how {
+                    Method::Rsrangefit => {
+                        let (min, max) = vs.minmax().into_option().unwrap();
+                        (max - min).powi(2)
                     }
-                })
-                .sum::<Float>();
-
-            match how {
-                Method::Rsrangefit => (f / n_buffer as Float).sqrt(),
-                Method::Dfa => (f / (n_buffer * t) as Float).sqrt(),
-            }
-        })
-        .collect_vec();
+                    Method::Dfa => vs.map(|v| v.powi(2)).sum::<F>(),
+                }
+            })
+            .sum::<F>();
+
+        *f_out = match how {
+            Method::Rsrangefit => (f / F::from_usize(n_buffer)).sqrt(),
+            Method::Dfa => (f / F::from_usize(n_buffer * t)).sqrt(),
+        };
+    }
 
     const MIN_POINTS: usize = 6;
-    let log_tau = tau.iter().map(|&t| (t as Float).ln()).collect_vec();
-    let log_fs = fs.iter().map(|&f| f.ln()).collect_vec();
-
-    let mut sserr = vec![];
-    for i in MIN_POINTS..(tau.len() - MIN_POINTS) {
-        sserr.push(
-            linear_regression_square_error(
-                &log_tau[0..i],
-                &log_fs[0..i].iter().map(|&t| t as Float).collect_vec(),
-            )
-            .sqrt()
-                + linear_regression_square_error(
-                    &log_tau[(i - 1)..],
-                    &log_fs[(i - 1)..].iter().map(|&t| t as Float).collect_vec(),
-                )
-                .sqrt(),
-        );
+    let mut log_tau = [F::zero(); N_TAU_STEPS];
+    let mut log_fs = [F::zero(); N_TAU_STEPS];
+    for ((lt, lf), (&t, &f)) in log_tau.iter_mut().zip(&mut log_fs).zip(tau.iter().zip(&fs)) {
+        *lt = F::from_usize(t).ln();
+        *lf = f.ln();
     }
+    let (log_tau, log_fs) = (&log_tau[..tau.len()], &log_fs[..tau.len()]);
 
-    let first_min_index = sserr
-        .iter()
-        .position_min_by_key(|&&x| UnwrapOrd(x))
-        .unwrap()
+    let first_min_index = (MIN_POINTS..(tau.len() - MIN_POINTS))
+        .map(|i| {
+            linear_regression_square_error(&log_tau[0..i], &log_fs[0..i]).sqrt()
+                + linear_regression_square_error(&log_tau[(i - 1)..], &log_fs[(i - 1)..]).sqrt()
+        })
+        .position_min_by(total_cmp)
+        .unwrap_or(0)
         + MIN_POINTS
         - 1;
 
-    Ok((first_min_index as Float + 1.0) / (tau.len() as Float))
+    Ok(F::from_usize(first_min_index + 1) / F::from_usize(tau.len()))
 }
 
 #[cfg(test)]
@@ -134,7 +139,7 @@ mod tests {
 
     #[test]
     fn test_sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             sc_fluct_anal_2_rsrangefit_50_1_logi_prop_r1(&numbers).unwrap(),
@@ -145,7 +150,7 @@ mod tests {
 
     #[test]
     fn test_sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1_same_original() {
-        let numbers = load_test_data();
+        let numbers = load_test_data::<f64>();
 
         assert_abs_diff_eq!(
             sc_fluct_anal_2_dfa_50_1_2_logi_prop_r1(&numbers).unwrap(),
@@ -1 +1,142 @@
+use crate::context::SeriesContext;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::utility::{any_nan, nextpow2};
 
+// The reference implementation uses this truncated value instead of the exact PI.
+#[allow(clippy::approx_constant)]
+const PI: f64 = 3.14159265359;
+
+enum Summary {
+    Centroid,
+    Area5_1,
+}
+
+pub fn sp_summaries_welch_rect_area_5_1<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sp_summaries_welch_rect_area_5_1_with_context(&SeriesContext::new(values))
+}
+
+pub fn sp_summaries_welch_rect_area_5_1_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    sp_summaries_welch_rect(ctx, Summary::Area5_1)
+}
+
+pub fn sp_summaries_welch_rect_centroid<F: Float>(values: &[F]) -> Catch22Result<F> {
+    sp_summaries_welch_rect_centroid_with_context(&SeriesContext::new(values))
+}
+
+pub fn sp_summaries_welch_rect_centroid_with_context<F: Float>(
+    ctx: &SeriesContext<F>,
+) -> Catch22Result<F> {
+    sp_summaries_welch_rect(ctx, Summary::Centroid)
+}
+
+fn sp_summaries_welch_rect<F: Float>(ctx: &SeriesContext<F>, what: Summary) -> Catch22Result<F> {
+    let values = ctx.values();
+    if values.len() < 2 {
+        return Err(Catch22Error::SizeUnder {
+            len: values.len(),
+            expect_minimum: 2,
+        });
+    }
+    if any_nan(values) {
+        return Ok(F::nan());
+    }
+
+    let mut pxx = ctx.floats();
+    let df = welch_rect(ctx, &mut pxx)?;
+
+    let two_pi = F::from_f64(2.0 * PI);
+    let w = |i: usize| two_pi * (F::from_usize(i) * df);
+    let sw = || pxx.iter().map(|&s| s / two_pi);
+    if sw().any(|s| s.is_infinite()) {
+        return Ok(F::zero());
+    }
+
+    let dw = w(1) - w(0);
+
+    Ok(match what {
+        Summary::Centroid => {
+            let cs_s = || {
+                sw().scan(F::zero(), |sum, s| {
+                    *sum += s;
+                    Some(*sum)
+                })
+            };
+            let threshold = cs_s().last().unwrap() * F::from_f64(0.5);
+
+            cs_s()
+                .enumerate()
+                .find_map(|(i, c)| (c > threshold).then(|| w(i)))
+                .unwrap_or(F::zero())
+        }
+        Summary::Area5_1 => sw().take(pxx.len() / 5).sum::<F>() * dw,
+    })
+}
+
+/// Welch power spectrum with a single rectangular window spanning the whole series.
+///
+/// Writes the one-sided power spectral density into `pxx` and returns the step between its
+/// frequencies (sampling frequency of 1).
+fn welch_rect<F: Float>(ctx: &SeriesContext<F>, pxx: &mut Vec<F>) -> Catch22Result<F> {
+    let values = ctx.values();
+    let n_fft = nextpow2(values.len()).ok_or(Catch22Error::SizeOver(values.len()))?;
+    let df = F::one() / F::from_usize(n_fft);
+    let mean = ctx.mean().unwrap();
+
+    // A window as wide as the series yields exactly one segment, and the squared norm
+    // of the rectangular window is its length.
+    let kmu = F::from_usize(values.len());
+
+    let mut padded = ctx.floats();
+    padded.extend(values.iter().map(|&y| y - mean));
+    padded.resize(n_fft, F::zero());
+    let mut f = ctx.complexes();
+    ctx.with_fft_planner(|planner| planner.real_fft(&padded, &mut f));
+
+    let n_out = n_fft / 2 + 1;
+    pxx.clear();
+    pxx.extend(f.iter().enumerate().map(|(i, x)| {
+        let p = x.norm_sqr() / kmu;
+        if i > 0 && i < n_out - 1 {
+            p * F::from_f64(2.0)
+        } else {
+            p
+        }
+    }));
+
+    Ok(df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::load_test_data;
+    use crate::utility::zscore;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_sp_summaries_welch_rect_area_5_1_same_original() {
+        // The reference value is computed on the z-scored series. The test data is only
+        // standardised to about 1e-6, and the area scales with the variance.
+        let numbers = zscore(&load_test_data::<f64>()).unwrap();
+
+        assert_abs_diff_eq!(
+            sp_summaries_welch_rect_area_5_1(&numbers).unwrap(),
+            0.99313877887424,
+            epsilon = 1e-12
+        );
+    }
+
+    #[test]
+    fn test_sp_summaries_welch_rect_centroid_same_original() {
+        let numbers = load_test_data::<f64>();
+
+        assert_abs_diff_eq!(
+            sp_summaries_welch_rect_centroid(&numbers).unwrap(),
+            0.03681553890926,
+            epsilon = 1e-12
+        );
+    }
+}
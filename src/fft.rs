@@ -1,19 +1,38 @@
+use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
 use itertools::Itertools;
-use num::Complex;
+use num::{Complex, Zero};
+use std::collections::HashMap;
 use std::f64::consts::PI;
 
-pub fn fft(values: &mut [Complex<Float>], size: usize, tw: &[Complex<Float>]) {
+/// Largest prime factor handled by the mixed radix transform. Lengths with a larger
+/// prime factor go through Bluestein's algorithm instead.
+const MAX_RADIX: usize = 31;
+
+/// Power of two transform; `size` must be a power of two and `tw` come from
+/// [`twiddles`].
+pub fn fft<F: Float>(values: &mut [Complex<F>], size: usize, tw: &[Complex<F>]) {
     let mut working = values.to_vec();
-    _fft(values, &mut working, size, 1, tw);
+    fft_with_scratch(values, &mut working, size, tw);
 }
 
-fn _fft(
-    values: &mut [Complex<Float>],
-    working: &mut [Complex<Float>],
+/// [`fft`] using a caller provided working buffer of the same length.
+pub fn fft_with_scratch<F: Float>(
+    values: &mut [Complex<F>],
+    working: &mut [Complex<F>],
+    size: usize,
+    tw: &[Complex<F>],
+) {
+    working.copy_from_slice(values);
+    _fft(values, working, size, 1, tw);
+}
+
+fn _fft<F: Float>(
+    values: &mut [Complex<F>],
+    working: &mut [Complex<F>],
     size: usize,
     step: usize,
-    tw: &[Complex<Float>],
+    tw: &[Complex<F>],
 ) {
     if step < size {
         _fft(working, values, size, step * 2, tw);
@@ -33,8 +52,466 @@ fn _fft(
     }
 }
 
-pub fn twiddles(size: usize) -> Vec<Complex<Float>> {
+pub fn twiddles<F: Float>(size: usize) -> Vec<Complex<F>> {
+    // computed in double precision so that single precision transforms are not
+    // affected by the rounding of the angle
     (0..size)
-        .map(|i| Complex::new(0.0, -PI * (i as Float / size as Float)).exp())
+        .map(|i| {
+            let theta = -PI * (i as f64 / size as f64);
+            Complex::new(F::from_f64(theta.cos()), F::from_f64(theta.sin()))
+        })
         .collect_vec()
 }
+
+/// `exp(-2πi k / n)` for `k` in `0..n`.
+pub fn unit_roots<F: Float>(n: usize) -> Vec<Complex<F>> {
+    (0..n).map(|k| unit_root(k, n)).collect_vec()
+}
+
+fn unit_root<F: Float>(k: usize, n: usize) -> Complex<F> {
+    let theta = -2.0 * PI * (k as f64 / n as f64);
+    Complex::new(F::from_f64(theta.cos()), F::from_f64(theta.sin()))
+}
+
+/// Smallest length not below `n` whose prime factors are all 2, 3 or 5.
+pub fn next_fast_len(n: usize) -> Option<usize> {
+    let mut best = n.checked_next_power_of_two()?;
+    let mut p5 = 1usize;
+    while p5 < best {
+        let mut p35 = p5;
+        while p35 < best {
+            // the smallest power of two bringing p35 to at least n
+            let mut candidate = p35;
+            while candidate < n {
+                candidate *= 2;
+            }
+            best = best.min(candidate);
+            p35 = match p35.checked_mul(3) {
+                Some(p) => p,
+                None => break,
+            };
+        }
+        p5 = match p5.checked_mul(5) {
+            Some(p) => p,
+            None => break,
+        };
+    }
+    Some(best)
+}
+
+/// Prime factors of `n` in ascending order, or `None` if one exceeds [`MAX_RADIX`].
+fn radices(mut n: usize) -> Option<Vec<usize>> {
+    let mut factors = vec![];
+    let mut p = 2;
+    while n > 1 {
+        if p > MAX_RADIX {
+            return None;
+        }
+        while n.is_multiple_of(p) {
+            factors.push(p);
+            n /= p;
+        }
+        p += 1;
+    }
+    Some(factors)
+}
+
+enum Algorithm<F> {
+    MixedRadix {
+        factors: Vec<usize>,
+        roots: Vec<Complex<F>>,
+    },
+    Bluestein {
+        chirp: Vec<Complex<F>>,
+        /// Transform of the conjugated chirp, zero padded to the power of two length.
+        kernel: Vec<Complex<F>>,
+        twiddles: Vec<Complex<F>>,
+    },
+}
+
+impl<F: Float> Algorithm<F> {
+    fn new(n: usize) -> Self {
+        match radices(n) {
+            Some(factors) => Self::MixedRadix {
+                factors,
+                roots: unit_roots(n),
+            },
+            None => {
+                let m = (2 * n - 1).next_power_of_two();
+
+                // exp(-πi k² / n), with k² reduced modulo 2n to keep the angle small
+                let chirp = (0..n)
+                    .map(|k| {
+                        let k2 = (k as u128 * k as u128 % (2 * n as u128)) as f64;
+                        let theta = -PI * k2 / n as f64;
+                        Complex::new(F::from_f64(theta.cos()), F::from_f64(theta.sin()))
+                    })
+                    .collect_vec();
+
+                let mut kernel = vec![Complex::zero(); m];
+                kernel[0] = chirp[0].conj();
+                for k in 1..n {
+                    kernel[k] = chirp[k].conj();
+                    kernel[m - k] = chirp[k].conj();
+                }
+                let twiddles = twiddles(m);
+                fft(&mut kernel, m, &twiddles);
+
+                Self::Bluestein {
+                    chirp,
+                    kernel,
+                    twiddles,
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Scratch<F> {
+    input: Vec<Complex<F>>,
+    working: Vec<Complex<F>>,
+    butterfly: Vec<Complex<F>>,
+    convolution: Vec<Complex<F>>,
+}
+
+/// Caches the twiddle tables of every length it has transformed, together with the
+/// scratch memory the transforms need, so that repeated transforms do not allocate.
+///
+/// The tables take memory proportional to their length and are kept until
+/// [`FftPlanner::clear`], so a long-lived planner fed with many distinct lengths grows
+/// with them. [`FftPlanner::with_plan_limit`] bounds the number of lengths it keeps.
+#[derive(Default)]
+pub struct FftPlanner<F> {
+    plans: HashMap<usize, Algorithm<F>>,
+    real_roots: HashMap<usize, Vec<Complex<F>>>,
+    plan_limit: Option<usize>,
+    scratch: Scratch<F>,
+    half: Vec<Complex<F>>,
+}
+
+impl<F: Float> FftPlanner<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A planner keeping the tables of at most `limit` lengths (and at least one). Planning
+    /// a new length when the limit is reached drops every cached table first.
+    pub fn with_plan_limit(limit: usize) -> Self {
+        Self {
+            plan_limit: Some(limit),
+            ..Self::default()
+        }
+    }
+
+    /// Drops the cached tables and scratch memory.
+    pub fn clear(&mut self) {
+        self.plans = HashMap::new();
+        self.real_roots = HashMap::new();
+        self.scratch = Scratch::default();
+        self.half = vec![];
+    }
+
+    /// Number of lengths whose complex transform is planned.
+    pub fn num_plans(&self) -> usize {
+        self.plans.len()
+    }
+
+    /// Forward transform of any length, in place.
+    pub fn fft(&mut self, values: &mut [Complex<F>]) {
+        let n = values.len();
+        if n <= 1 {
+            return;
+        }
+
+        make_room(&mut self.plans, n, self.plan_limit);
+        let algorithm = self.plans.entry(n).or_insert_with(|| Algorithm::new(n));
+        let scratch = &mut self.scratch;
+        match algorithm {
+            Algorithm::MixedRadix { factors, roots } => {
+                scratch.input.clear();
+                scratch.input.extend_from_slice(values);
+                scratch
+                    .butterfly
+                    .resize(factors.iter().copied().max().unwrap(), Complex::zero());
+                mixed_radix(
+                    &scratch.input,
+                    1,
+                    values,
+                    factors,
+                    roots,
+                    1,
+                    &mut scratch.butterfly,
+                );
+            }
+            Algorithm::Bluestein {
+                chirp,
+                kernel,
+                twiddles,
+            } => {
+                let m = kernel.len();
+                let a = &mut scratch.convolution;
+                a.clear();
+                a.extend(values.iter().zip(chirp.iter()).map(|(&x, &c)| x * c));
+                a.resize(m, Complex::zero());
+                scratch.working.resize(m, Complex::zero());
+
+                fft_with_scratch(a, &mut scratch.working, m, twiddles);
+                a.iter_mut()
+                    .zip(kernel.iter())
+                    .for_each(|(a, &b)| *a = (*a * b).conj());
+                fft_with_scratch(a, &mut scratch.working, m, twiddles);
+
+                let scale = F::from_usize(m);
+                for (x, (&a, &c)) in values.iter_mut().zip(a.iter().zip(chirp.iter())) {
+                    *x = c * a.conj() / scale;
+                }
+            }
+        }
+    }
+
+    /// Inverse of [`FftPlanner::fft`], including the `1 / n` normalisation.
+    pub fn ifft(&mut self, values: &mut [Complex<F>]) {
+        values.iter_mut().for_each(|x| *x = x.conj());
+        self.fft(values);
+        let scale = F::from_usize(values.len());
+        values.iter_mut().for_each(|x| *x = x.conj() / scale);
+    }
+
+    /// Forward transform of a real series into the `n / 2 + 1` bins of non-negative
+    /// frequency. Even lengths are computed with a complex transform of half the length.
+    pub fn real_fft(&mut self, values: &[F], output: &mut Vec<Complex<F>>) {
+        let n = values.len();
+        output.clear();
+        if n % 2 == 1 {
+            output.extend(values.iter().map(|&v| Complex::new(v, F::zero())));
+            self.fft(output);
+            output.truncate(n / 2 + 1);
+            return;
+        }
+        if n == 0 {
+            return;
+        }
+
+        let h = n / 2;
+        let mut z = std::mem::take(&mut self.half);
+        z.clear();
+        z.extend(values.chunks_exact(2).map(|c| Complex::new(c[0], c[1])));
+        self.fft(&mut z);
+
+        let roots = self.real_roots(n);
+        let half = F::from_f64(0.5);
+        output.extend((0..=h).map(|k| {
+            let zk = z[k % h];
+            let zc = z[(h - k) % h].conj();
+            let even = (zk + zc) * half;
+            let odd = (zk - zc) * Complex::new(F::zero(), -half);
+            even + roots[k] * odd
+        }));
+        self.half = z;
+    }
+
+    /// Inverse of [`FftPlanner::real_fft`]: recovers the real series of length `n` from
+    /// its `n / 2 + 1` bins of non-negative frequency.
+    pub fn inverse_real_fft(
+        &mut self,
+        spectrum: &[Complex<F>],
+        n: usize,
+        output: &mut Vec<F>,
+    ) -> Catch22Result<()> {
+        if spectrum.len() != n / 2 + 1 {
+            return Err(Catch22Error::LengthMismatch(spectrum.len(), n / 2 + 1));
+        }
+        output.clear();
+        let mut z = std::mem::take(&mut self.half);
+        z.clear();
+
+        if n % 2 == 1 {
+            z.extend(spectrum.iter().copied());
+            z.extend(spectrum[1..].iter().rev().map(|x| x.conj()));
+            self.ifft(&mut z);
+            output.extend(z.iter().map(|x| x.re));
+        } else if n > 0 {
+            let h = n / 2;
+            let roots = self.real_roots(n);
+            let half = F::from_f64(0.5);
+            z.extend((0..h).map(|k| {
+                let xk = spectrum[k];
+                let xc = spectrum[h - k].conj();
+                let even = (xk + xc) * half;
+                let odd = (xk - xc) * roots[k].conj() * half;
+                even + odd * Complex::new(F::zero(), F::one())
+            }));
+            self.ifft(&mut z);
+            output.extend(z.iter().flat_map(|x| [x.re, x.im]));
+        }
+        self.half = z;
+        Ok(())
+    }
+
+    fn real_roots(&mut self, n: usize) -> &[Complex<F>] {
+        make_room(&mut self.real_roots, n, self.plan_limit);
+        self.real_roots
+            .entry(n)
+            .or_insert_with(|| (0..=n / 2).map(|k| unit_root(k, n)).collect_vec())
+    }
+}
+
+/// Empties `cache` if it is full and has no entry for `n`.
+fn make_room<T>(cache: &mut HashMap<usize, T>, n: usize, limit: Option<usize>) {
+    if limit.is_some_and(|limit| cache.len() >= limit) && !cache.contains_key(&n) {
+        cache.clear();
+    }
+}
+
+/// Decimation in time: splits `output` into `p` interleaved sub-transforms of the
+/// input, then recombines them with a radix `p` butterfly.
+fn mixed_radix<F: Float>(
+    input: &[Complex<F>],
+    stride: usize,
+    output: &mut [Complex<F>],
+    factors: &[usize],
+    roots: &[Complex<F>],
+    root_step: usize,
+    scratch: &mut [Complex<F>],
+) {
+    let Some((&p, rest)) = factors.split_first() else {
+        output[0] = input[0];
+        return;
+    };
+
+    let n = output.len();
+    let m = n / p;
+    for (r, chunk) in output.chunks_exact_mut(m).enumerate() {
+        mixed_radix(
+            &input[r * stride..],
+            stride * p,
+            chunk,
+            rest,
+            roots,
+            root_step * p,
+            scratch,
+        );
+    }
+
+    for k in 0..m {
+        for (q, s) in scratch[..p].iter_mut().enumerate() {
+            let index = (k + q * m) * root_step;
+            *s = (0..p)
+                .map(|r| output[r * m + k] * roots[(r * index) % roots.len()])
+                .sum();
+        }
+        for (q, &s) in scratch[..p].iter().enumerate() {
+            output[k + q * m] = s;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    fn naive_dft(values: &[Complex<f64>]) -> Vec<Complex<f64>> {
+        let n = values.len();
+        (0..n)
+            .map(|k| {
+                values
+                    .iter()
+                    .enumerate()
+                    .map(|(j, &x)| x * unit_root::<f64>(j * k % n, n))
+                    .sum()
+            })
+            .collect_vec()
+    }
+
+    fn signal(n: usize) -> Vec<f64> {
+        (0..n)
+            .map(|i| (i as f64 * 0.37).sin() + (i % 7) as f64 * 0.1)
+            .collect_vec()
+    }
+
+    #[test]
+    fn test_planner_fft_same_as_naive_dft() {
+        let mut planner = FftPlanner::new();
+        // every length twice, so that cached plans and scratch memory are reused
+        for n in (1..=64).chain([97, 128, 270, 1021]).flat_map(|n| [n, n]) {
+            let values = signal(n)
+                .iter()
+                .zip(signal(n + 3).iter().skip(3))
+                .map(|(&re, &im)| Complex::new(re, im))
+                .collect_vec();
+            let expected = naive_dft(&values);
+
+            let mut actual = values.clone();
+            planner.fft(&mut actual);
+            for (a, e) in actual.iter().zip(expected.iter()) {
+                assert_abs_diff_eq!(a.re, e.re, epsilon = 1e-9);
+                assert_abs_diff_eq!(a.im, e.im, epsilon = 1e-9);
+            }
+
+            planner.ifft(&mut actual);
+            for (a, e) in actual.iter().zip(values.iter()) {
+                assert_abs_diff_eq!(a.re, e.re, epsilon = 1e-12);
+                assert_abs_diff_eq!(a.im, e.im, epsilon = 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_real_fft_round_trip() {
+        for n in (1..=40).chain([97, 270, 1021]) {
+            let values = signal(n);
+            let complex = values.iter().map(|&v| Complex::new(v, 0.0)).collect_vec();
+            let expected = naive_dft(&complex);
+
+            let mut spectrum = vec![];
+            FftPlanner::new().real_fft(&values, &mut spectrum);
+            assert_eq!(spectrum.len(), n / 2 + 1);
+            for (a, e) in spectrum.iter().zip(expected.iter()) {
+                assert_abs_diff_eq!(a.re, e.re, epsilon = 1e-9);
+                assert_abs_diff_eq!(a.im, e.im, epsilon = 1e-9);
+            }
+
+            let mut restored = vec![];
+            FftPlanner::new()
+                .inverse_real_fft(&spectrum, n, &mut restored)
+                .unwrap();
+            for (&a, &e) in restored.iter().zip(values.iter()) {
+                assert_abs_diff_eq!(a, e, epsilon = 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    fn test_planner_plan_limit() {
+        let mut limited = FftPlanner::with_plan_limit(3);
+        let mut unlimited = FftPlanner::new();
+        for n in (2..20).chain(2..20) {
+            let values = signal(n)
+                .iter()
+                .map(|&re| Complex::new(re, 0.0))
+                .collect_vec();
+            let (mut a, mut b) = (values.clone(), values);
+            limited.fft(&mut a);
+            unlimited.fft(&mut b);
+            assert_eq!(a, b);
+            assert!(limited.num_plans() <= 3);
+        }
+        assert_eq!(unlimited.num_plans(), 18);
+
+        unlimited.clear();
+        assert_eq!(unlimited.num_plans(), 0);
+    }
+
+    #[test]
+    fn test_next_fast_len() {
+        assert_eq!(next_fast_len(0), Some(1));
+        assert_eq!(next_fast_len(1), Some(1));
+        assert_eq!(next_fast_len(7), Some(8));
+        assert_eq!(next_fast_len(11), Some(12));
+        assert_eq!(next_fast_len(270), Some(270));
+        assert_eq!(next_fast_len(271), Some(288));
+        assert_eq!(next_fast_len(1025), Some(1080));
+    }
+}
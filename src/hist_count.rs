@@ -1,26 +1,33 @@
 use crate::error::{Catch22Error, Catch22Result};
 use crate::primitive::Float;
 use itertools::Itertools;
-use num::ToPrimitive;
-use std::convert::TryInto;
 
-pub struct BinData<const B: usize, const BN: usize> {
+/// Most bins a histogram may have; more are rejected with
+/// [`Catch22Error::InvalidUsizeParameter`] rather than allocated.
+pub const MAX_BINS: usize = 1 << 24;
+
+pub struct BinData<F, const B: usize, const BN: usize> {
     pub count: [usize; B],
-    pub edges: [Float; BN],
+    pub edges: [F; BN],
+}
+
+pub struct JointBinData<F, const B: usize, const BN: usize> {
+    pub count: [[usize; B]; B],
+    pub edges: [F; BN],
 }
 
-pub struct BinDataFlexible {
+pub struct BinDataFlexible<F> {
     pub count: Vec<usize>,
-    pub edges: Vec<Float>,
+    pub edges: Vec<F>,
 }
 
-pub struct NormalizedBinDataFlexible {
-    pub count: Vec<Float>,
-    pub edges: Vec<Float>,
+pub struct NormalizedBinDataFlexible<F> {
+    pub count: Vec<F>,
+    pub edges: Vec<F>,
 }
 
-impl BinDataFlexible {
-    pub fn normalize(self, size: usize) -> Catch22Result<NormalizedBinDataFlexible> {
+impl<F: Float> BinDataFlexible<F> {
+    pub fn normalize(self, size: usize) -> Catch22Result<NormalizedBinDataFlexible<F>> {
         if size == 0 {
             return Err(Catch22Error::EmptyInput);
         }
@@ -28,67 +35,154 @@ impl BinDataFlexible {
             count: self
                 .count
                 .iter()
-                .map(|&c| c as Float / size as Float)
+                .map(|&c| F::from_usize(c) / F::from_usize(size))
                 .collect(),
             edges: self.edges,
         })
     }
 }
 
-impl NormalizedBinDataFlexible {
+impl<F> NormalizedBinDataFlexible<F> {
     #[allow(clippy::len_without_is_empty)]
     pub fn len(&self) -> usize {
         self.count.len()
     }
 }
 
-pub fn hist_count_flexible(values: &[Float], bins: usize) -> Catch22Result<BinDataFlexible> {
+pub fn hist_count_flexible<F: Float>(
+    values: &[F],
+    bins: usize,
+) -> Catch22Result<BinDataFlexible<F>> {
+    let mut count = vec![];
+    let (min, bin_step) = hist_count_flexible_into(values, bins, &mut count)?;
+
+    let edges = (0..(bins + 1))
+        .map(|i| F::from_usize(i) * bin_step + min)
+        .collect_vec();
+
+    Ok(BinDataFlexible { count, edges })
+}
+
+/// Same as [`hist_count_flexible`], counting into `count` and returning the lowest edge
+/// and the bin width in place of the edges.
+pub(crate) fn hist_count_flexible_into<F: Float>(
+    values: &[F],
+    bins: usize,
+    count: &mut Vec<usize>,
+) -> Catch22Result<(F, F)> {
+    if bins == 0 || bins > MAX_BINS {
+        return Err(Catch22Error::InvalidUsizeParameter {
+            name: "bins",
+            value: bins,
+        });
+    }
     let (&min, &max) = values
         .iter()
         .minmax()
         .into_option()
         .ok_or(Catch22Error::EmptyInput)?;
 
-    let bin_step = (max - min) / bins as f64;
+    let bin_step = (max - min) / F::from_usize(bins);
 
-    let mut count = vec![0usize; bins];
-    for v in values {
-        let bin_index = ((v - min) / bin_step)
-            .to_usize()
-            .unwrap()
-            .max(0)
-            .min(bins - 1);
-        count[bin_index] += 1;
+    count.clear();
+    count.resize(bins, 0);
+    for &v in values {
+        count[bin_index(v, min, bin_step, bins)] += 1;
     }
 
-    let edges = (0..(bins + 1))
-        .map(|i| i as Float * bin_step + min)
-        .collect_vec();
-
-    Ok(BinDataFlexible { count, edges })
+    Ok((min, bin_step))
 }
 
-pub fn hist_counts<const B: usize, const BN: usize>(y: &[Float]) -> Catch22Result<BinData<B, BN>> {
+pub fn hist_counts<F: Float, const B: usize, const BN: usize>(
+    y: &[F],
+) -> Catch22Result<BinData<F, B, BN>> {
+    if B == 0 {
+        return Err(Catch22Error::InvalidUsizeParameter {
+            name: "bins",
+            value: B,
+        });
+    }
     let (&min, &max) = y
         .iter()
         .minmax()
         .into_option()
         .ok_or(Catch22Error::EmptyInput)?;
 
-    let bin_step = (max - min) / B as f64;
+    let bin_step = (max - min) / F::from_usize(B);
 
     let mut count: [usize; B] = [0usize; B];
 
-    for v in y {
-        let bin_index = ((v - min) / bin_step).to_usize().unwrap().max(0).min(B - 1);
-        count[bin_index] += 1;
+    for &v in y {
+        count[bin_index(v, min, bin_step, B)] += 1;
     }
 
-    let edges = (0..BN)
-        .map(|i| i as Float * bin_step + min)
-        .collect_vec()
-        .try_into()
-        .unwrap();
+    let edges = std::array::from_fn(|i| F::from_usize(i) * bin_step + min);
 
     Ok(BinData { edges, count })
 }
+
+/// Bin of `v` for bins of width `bin_step` starting at `min`, clamped to the valid bins.
+///
+/// A zero width (constant series) or an overflowing range puts the value in the first bin,
+/// like the integer conversion of the reference implementation.
+#[allow(clippy::unnecessary_min_or_max)]
+fn bin_index<F: Float>(v: F, min: F, bin_step: F, bins: usize) -> usize {
+    ((v - min) / bin_step).as_usize().max(0).min(bins - 1)
+}
+
+/// Joint histogram of the pairs `(y[t], y[t + lag])` over `B` even bins spanning `[min, max)`.
+///
+/// Values outside of the range are not counted.
+pub fn joint_hist_counts_lagged<F: Float, const B: usize, const BN: usize>(
+    y: &[F],
+    lag: usize,
+    min: F,
+    max: F,
+) -> Catch22Result<JointBinData<F, B, BN>> {
+    if y.is_empty() {
+        return Err(Catch22Error::EmptyInput);
+    }
+    if y.len() <= lag {
+        return Err(Catch22Error::SizeUnder {
+            len: y.len(),
+            expect_minimum: lag.saturating_add(1),
+        });
+    }
+
+    let bin_step = (max - min) / F::from_usize(B);
+    let edges: [F; BN] = std::array::from_fn(|i| F::from_usize(i) * bin_step + min);
+
+    let bin_index = |v: F| edges.iter().position(|&e| v < e)?.checked_sub(1);
+
+    let mut count = [[0usize; B]; B];
+    for (&a, &b) in y.iter().zip(y.iter().skip(lag)) {
+        if let (Some(i), Some(j)) = (bin_index(a), bin_index(b)) {
+            count[i][j] += 1;
+        }
+    }
+
+    Ok(JointBinData { count, edges })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::arbitrary_series;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_hist_counts_never_panic(
+            values in arbitrary_series(),
+            bins in prop_oneof![0..20usize, Just(MAX_BINS + 1), Just(usize::MAX), any::<usize>()],
+        ) {
+            if let Ok(hist) = hist_counts::<f64, 5, 6>(&values) {
+                prop_assert_eq!(hist.count.iter().sum::<usize>(), values.len());
+            }
+            if let Ok(hist) = hist_count_flexible(&values, bins) {
+                prop_assert_eq!(hist.count.iter().sum::<usize>(), values.len());
+            }
+            let _ = joint_hist_counts_lagged::<f64, 5, 6>(&values, bins, -1.0, 1.0);
+        }
+    }
+}
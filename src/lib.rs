@@ -1,8 +1,30 @@
+#[cfg(feature = "ndarray")]
+pub mod array;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+#[cfg(feature = "rayon")]
+pub mod batch;
+pub mod catch22;
+pub mod context;
 pub mod error;
 pub mod features;
 pub(crate) mod fft;
 pub mod hist_count;
+pub mod missing;
+#[cfg(feature = "polars")]
+pub mod polars;
 pub mod primitive;
+pub mod spline;
 pub(crate) mod stat;
+pub mod streaming;
 pub(crate) mod test_utility;
 pub mod utility;
+pub mod window;
+pub mod workspace;
+
+pub use catch22::{
+    catch22_all, catch22_all_with, catch22_all_with_planner, catch22_select,
+    catch22_select_with_planner, Catch22Options, Catch22Output,
+};
+pub use fft::FftPlanner;
+pub use workspace::Workspace;
@@ -0,0 +1,158 @@
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use std::borrow::Cow;
+
+/// How NaN samples are handled before the features are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MissingPolicy {
+    /// Keep them; every feature of a series containing NaN is NaN.
+    #[default]
+    Propagate,
+    /// Fail with [`Catch22Error::MissingValue`].
+    Error,
+    /// Remove them, joining the remaining samples.
+    Drop,
+    /// Replace them by linear interpolation between the nearest valid samples. Missing
+    /// samples before the first or after the last valid sample take its value.
+    Interpolate,
+    /// Replace them by the previous valid sample. Missing samples before the first valid
+    /// sample take its value.
+    ForwardFill,
+}
+
+/// How ±Inf samples are handled before the features are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InfinitePolicy {
+    /// Keep them as they are.
+    #[default]
+    Allow,
+    /// Fail with [`Catch22Error::InfiniteValue`].
+    Error,
+    /// Treat them as missing, according to the [`MissingPolicy`].
+    Missing,
+}
+
+/// Applies `missing` and `infinite` to `values`, borrowing them if nothing changes.
+pub fn apply_missing_policy<F: Float>(
+    values: &[F],
+    missing: MissingPolicy,
+    infinite: InfinitePolicy,
+) -> Catch22Result<Cow<'_, [F]>> {
+    let mut output = vec![];
+    Ok(
+        if apply_missing_policy_into(values, missing, infinite, &mut output)? {
+            Cow::Owned(output)
+        } else {
+            Cow::Borrowed(values)
+        },
+    )
+}
+
+/// Same as [`apply_missing_policy`], writing into `output` so that its allocation can be
+/// reused. Returns `false`, leaving `output` untouched, if `values` can be used as they are.
+pub(crate) fn apply_missing_policy_into<F: Float>(
+    values: &[F],
+    missing: MissingPolicy,
+    infinite: InfinitePolicy,
+    output: &mut Vec<F>,
+) -> Catch22Result<bool> {
+    let is_missing = |x: F| x.is_nan() || (infinite == InfinitePolicy::Missing && x.is_infinite());
+
+    if infinite == InfinitePolicy::Error {
+        if let Some(i) = values.iter().position(|x| x.is_infinite()) {
+            return Err(Catch22Error::InfiniteValue(i));
+        }
+    }
+    let Some(first_missing) = values.iter().position(|&x| is_missing(x)) else {
+        return Ok(false);
+    };
+
+    let valid = || values.iter().enumerate().filter(|&(_, &x)| !is_missing(x));
+    let (first_valid, &first_value) = match missing {
+        MissingPolicy::Propagate => {
+            if infinite != InfinitePolicy::Missing {
+                return Ok(false);
+            }
+            output.clear();
+            output.extend(
+                values
+                    .iter()
+                    .map(|&x| if is_missing(x) { F::nan() } else { x }),
+            );
+            return Ok(true);
+        }
+        MissingPolicy::Error => return Err(Catch22Error::MissingValue(first_missing)),
+        MissingPolicy::Drop => {
+            output.clear();
+            output.extend(valid().map(|(_, &x)| x));
+            return Ok(true);
+        }
+        MissingPolicy::Interpolate | MissingPolicy::ForwardFill => {
+            valid().next().ok_or(Catch22Error::EmptyInput)?
+        }
+    };
+
+    output.clear();
+    output.extend_from_slice(values);
+    let filled = output;
+    filled[..first_valid].fill(first_value);
+
+    let mut previous = (first_valid, first_value);
+    for (i, &x) in valid().skip(1) {
+        let (j, y) = previous;
+        for (k, slot) in filled.iter_mut().enumerate().take(i).skip(j + 1) {
+            *slot = match missing {
+                MissingPolicy::Interpolate => {
+                    y + (x - y) * F::from_usize(k - j) / F::from_usize(i - j)
+                }
+                _ => y,
+            };
+        }
+        previous = (i, x);
+    }
+    filled[previous.0 + 1..].fill(previous.1);
+
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_missing_policy() {
+        let nan = f64::NAN;
+        let values = [nan, 1.0, nan, nan, 4.0, f64::INFINITY, 6.0, nan];
+        let apply = |missing, infinite| apply_missing_policy(&values, missing, infinite);
+
+        assert_eq!(
+            apply(MissingPolicy::Interpolate, InfinitePolicy::Missing).unwrap()[..],
+            [1.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 6.0]
+        );
+        assert_eq!(
+            apply(MissingPolicy::ForwardFill, InfinitePolicy::Missing).unwrap()[..],
+            [1.0, 1.0, 1.0, 1.0, 4.0, 4.0, 6.0, 6.0]
+        );
+        assert_eq!(
+            apply(MissingPolicy::Drop, InfinitePolicy::Allow).unwrap()[..],
+            [1.0, 4.0, f64::INFINITY, 6.0]
+        );
+        assert!(matches!(
+            apply(MissingPolicy::Error, InfinitePolicy::Allow),
+            Err(Catch22Error::MissingValue(0))
+        ));
+        assert!(matches!(
+            apply(MissingPolicy::Drop, InfinitePolicy::Error),
+            Err(Catch22Error::InfiniteValue(5))
+        ));
+        assert!(apply(MissingPolicy::Propagate, InfinitePolicy::Missing).unwrap()[5].is_nan());
+        assert!(matches!(
+            apply(MissingPolicy::Propagate, InfinitePolicy::Allow).unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert!(matches!(
+            apply_missing_policy(&[nan], MissingPolicy::Interpolate, InfinitePolicy::Allow),
+            Err(Catch22Error::EmptyInput)
+        ));
+    }
+}
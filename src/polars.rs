@@ -0,0 +1,230 @@
+use crate::catch22::{catch22_all_with_planner, Catch22Options};
+use crate::error::Catch22Error;
+use crate::fft::FftPlanner;
+use itertools::Itertools;
+use polars_core::prelude::*;
+use polars_lazy::prelude::{apply_multiple, Expr};
+
+/// Computes the catch22 (or catch24) features of a Polars series as a struct series
+/// with one `Float64` field per feature.
+///
+/// A `List` series gives one row per list, as after `group_by(id).agg(col(value))`;
+/// any other numeric series is a single series and gives a single row, as in the
+/// aggregation context of `group_by(id).agg(catch22(value))`. Null values are treated
+/// as NaN; null lists and series whose extraction fails give null fields. The rows whose
+/// extraction failed are returned with their errors, in ascending order.
+pub fn catch22_series(
+    series: &Series,
+    options: Catch22Options,
+) -> PolarsResult<(Series, Vec<(usize, Catch22Error)>)> {
+    let features = options.features::<f64>();
+    let mut columns: Vec<Vec<Option<f64>>> = vec![vec![]; features.len()];
+    let mut errors = vec![];
+
+    let mut planner = FftPlanner::new();
+    let mut buffer = vec![];
+    let mut row = 0;
+    let mut push = |s: Option<Series>| -> PolarsResult<()> {
+        let output = match s {
+            Some(s) => {
+                let s = s.cast(&DataType::Float64)?;
+                buffer.clear();
+                buffer.extend(s.f64()?.iter().map(|x| x.unwrap_or(f64::NAN)));
+                catch22_all_with_planner(&buffer, options, &mut planner)
+                    .map_err(|e| errors.push((row, e)))
+                    .ok()
+            }
+            None => None,
+        };
+        row += 1;
+        for (j, column) in columns.iter_mut().enumerate() {
+            column.push(output.as_ref().map(|o| o.values[j]));
+        }
+        Ok(())
+    };
+
+    if let DataType::List(_) = series.dtype() {
+        for s in series.list()?.into_iter() {
+            push(s)?;
+        }
+    } else {
+        push(Some(series.clone()))?;
+    }
+
+    let fields = features
+        .iter()
+        .zip(columns)
+        .map(|(f, c)| Series::new(f.name.into(), c))
+        .collect_vec();
+    let len = fields[0].len();
+    let output = StructChunked::from_series(series.name().clone(), len, fields.iter())?;
+    Ok((output.into_series(), errors))
+}
+
+/// The `catch22` expression: the features of `expr` as a struct, one row per group in
+/// `group_by(id).agg([catch22(col(value))])` or a single row in a `select`.
+pub fn catch22(expr: Expr) -> Expr {
+    apply_multiple(
+        |columns| {
+            let inputs = columns
+                .iter()
+                .map(|c| c.as_materialized_series().clone())
+                .collect_vec();
+            catch22_expr(&inputs).map(Column::from)
+        },
+        [expr],
+        |_, fields| catch22_output_field(fields),
+        true,
+    )
+}
+
+/// Body of the `catch22` expression and of the plugin in `catch22-polars`: the features of
+/// the single input with the default options. The rows whose extraction failed are null,
+/// with a Polars warning giving the error.
+pub fn catch22_expr(inputs: &[Series]) -> PolarsResult<Series> {
+    let (output, errors) = catch22_series(single_input(inputs)?, Catch22Options::default())?;
+    for (row, e) in errors {
+        polars_warn!(UserWarning, "catch22: row {row}: {e}");
+    }
+    Ok(output)
+}
+
+/// Output field of the `catch22` expression and of the plugin in `catch22-polars`.
+pub fn catch22_output_field(input_fields: &[Field]) -> PolarsResult<Field> {
+    let fields = Catch22Options::default()
+        .features::<f64>()
+        .iter()
+        .map(|f| Field::new(f.name.into(), DataType::Float64))
+        .collect_vec();
+    Ok(Field::new(
+        single_input(input_fields)?.name().clone(),
+        DataType::Struct(fields),
+    ))
+}
+
+fn single_input<T>(inputs: &[T]) -> PolarsResult<&T> {
+    polars_ensure!(
+        inputs.len() == 1,
+        InvalidOperation: "catch22 takes a single input, got {}", inputs.len()
+    );
+    Ok(&inputs[0])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catch22::catch22_all;
+    use crate::test_utility::load_test_data;
+    use polars_lazy::prelude::{col, IntoLazy};
+
+    #[test]
+    fn test_catch22_series_of_one_group() {
+        let numbers = load_test_data::<f64>();
+        let series = Series::new("value".into(), &numbers);
+
+        let output = catch22_expr(std::slice::from_ref(&series)).unwrap();
+
+        assert_eq!(output.len(), 1);
+        assert_eq!(
+            output.dtype(),
+            catch22_output_field(&[series.field().into_owned()])
+                .unwrap()
+                .dtype()
+        );
+        let expected = catch22_all(&numbers).unwrap();
+        for (field, &value) in output
+            .struct_()
+            .unwrap()
+            .fields_as_series()
+            .iter()
+            .zip(expected.values.iter())
+        {
+            assert_eq!(field.f64().unwrap().get(0), Some(value));
+        }
+    }
+
+    #[test]
+    fn test_catch22_series_of_lists() {
+        let numbers = load_test_data::<f64>();
+        let lists = [
+            Series::new("".into(), &numbers[..200]),
+            Series::new("".into(), &[1.0]),
+            Series::new("".into(), &numbers[70..]),
+        ];
+        let series = Series::new("value".into(), &lists);
+
+        let (output, errors) = catch22_series(&series, Default::default()).unwrap();
+
+        assert_eq!(output.len(), 3);
+        assert_eq!(
+            errors,
+            [(
+                1,
+                Catch22Error::SizeUnder {
+                    len: 1,
+                    expect_minimum: 2
+                }
+            )]
+        );
+        let dfa = output
+            .struct_()
+            .unwrap()
+            .field_by_name("SC_FluctAnal_2_dfa_50_1_2_logi_prop_r1")
+            .unwrap();
+        assert_eq!(
+            dfa.f64().unwrap().into_iter().collect_vec(),
+            [
+                catch22_all(&numbers[..200])
+                    .unwrap()
+                    .values
+                    .get(19)
+                    .copied(),
+                None,
+                catch22_all(&numbers[70..]).unwrap().values.get(19).copied(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_catch22_in_group_by_agg() {
+        let numbers = load_test_data::<f64>();
+        let groups = [&numbers[..200], &numbers[50..150], &numbers[100..]];
+        let ids = groups
+            .iter()
+            .enumerate()
+            .flat_map(|(i, g)| std::iter::repeat_n(i as u32, g.len()))
+            .collect_vec();
+        let values = groups.concat();
+        let df = df!("id" => ids, "value" => values).unwrap();
+
+        let output = df
+            .lazy()
+            .group_by([col("id")])
+            .agg([catch22(col("value"))])
+            .sort(["id"], SortMultipleOptions::default())
+            .collect()
+            .unwrap();
+
+        let features = output.column("value").unwrap().struct_().unwrap().clone();
+        assert_eq!(features.len(), groups.len());
+        for (i, group) in groups.iter().enumerate() {
+            let expected = catch22_all(group).unwrap();
+            for (field, &value) in features
+                .fields_as_series()
+                .iter()
+                .zip(expected.values.iter())
+            {
+                assert_eq!(field.f64().unwrap().get(i), Some(value));
+            }
+        }
+    }
+
+    #[test]
+    fn test_catch22_expr_wrong_arity() {
+        let series = Series::new("value".into(), &[1.0, 2.0]);
+
+        assert!(catch22_expr(&[]).is_err());
+        assert!(catch22_expr(&[series.clone(), series.clone()]).is_err());
+        assert!(catch22_output_field(&[]).is_err());
+    }
+}
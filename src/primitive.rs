@@ -1 +1,53 @@
-pub type Float = f64;
+use num::traits::FloatConst;
+use std::fmt::{Debug, Display};
+use std::iter::Sum;
+use std::ops::{AddAssign, DivAssign, MulAssign, SubAssign};
+
+/// Floating point type the features are computed in, implemented for `f32` and `f64`.
+pub trait Float:
+    num::Float
+    + FloatConst
+    + Sum
+    + for<'a> Sum<&'a Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + DivAssign
+    + Default
+    + Debug
+    + Display
+    + Send
+    + Sync
+    + 'static
+{
+    fn from_f64(x: f64) -> Self;
+    fn from_usize(x: usize) -> Self;
+    /// Saturating conversion with the semantics of an `as` cast.
+    fn as_usize(self) -> usize;
+    fn as_f64(self) -> f64;
+}
+
+macro_rules! impl_float {
+    ($t:ty) => {
+        impl Float for $t {
+            fn from_f64(x: f64) -> Self {
+                x as $t
+            }
+
+            fn from_usize(x: usize) -> Self {
+                x as $t
+            }
+
+            fn as_usize(self) -> usize {
+                self as usize
+            }
+
+            fn as_f64(self) -> f64 {
+                self as f64
+            }
+        }
+    };
+}
+
+impl_float!(f32);
+impl_float!(f64);
@@ -0,0 +1,136 @@
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use crate::stat::least_squares;
+use itertools::Itertools;
+
+/// Cubic spline with continuous first and second derivatives at its breaks.
+///
+/// The spline is held in the truncated power basis `1, t, t^2, t^3, (t - k)^3_+` where `t`
+/// is the input rescaled so that the outer breaks map to `[0, 1]`.
+#[derive(Debug, Clone)]
+pub struct CubicSpline<F> {
+    origin: F,
+    scale: F,
+    knots: Vec<F>,
+    coefs: Vec<F>,
+}
+
+impl<F: Float> CubicSpline<F> {
+    pub fn evaluate(&self, x: F) -> F {
+        spline_evaluate(self.origin, self.scale, &self.knots, &self.coefs, x)
+    }
+}
+
+/// [`CubicSpline::evaluate`] for the parts written by [`spline_fit_into`].
+pub(crate) fn spline_evaluate<F: Float>(origin: F, scale: F, knots: &[F], coefs: &[F], x: F) -> F {
+    basis(knots, (x - origin) / scale)
+        .zip(coefs.iter())
+        .map(|(b, &c)| b * c)
+        .sum()
+}
+
+fn basis<F: Float>(knots: &[F], t: F) -> impl Iterator<Item = F> + '_ {
+    [F::one(), t, t * t, t * t * t]
+        .into_iter()
+        .chain(knots.iter().map(move |&k| (t - k).max(F::zero()).powi(3)))
+}
+
+/// Least-squares fit of a cubic spline with the given (strictly increasing) breaks
+/// to the points `(x, y)`.
+pub fn spline_fit<F: Float>(x: &[F], y: &[F], breaks: &[F]) -> Catch22Result<CubicSpline<F>> {
+    let mut knots = vec![];
+    let mut coefs = vec![];
+    let (origin, scale) = spline_fit_into(
+        x,
+        y,
+        breaks,
+        &mut knots,
+        &mut coefs,
+        &mut vec![],
+        &mut vec![],
+    )?;
+
+    Ok(CubicSpline {
+        origin,
+        scale,
+        knots,
+        coefs,
+    })
+}
+
+/// Same as [`spline_fit`], writing the knots and coefficients of the spline into `knots`
+/// and `coefs` and returning its origin and scale. `columns` and `work` are working memory.
+pub(crate) fn spline_fit_into<F: Float>(
+    x: &[F],
+    y: &[F],
+    breaks: &[F],
+    knots: &mut Vec<F>,
+    coefs: &mut Vec<F>,
+    columns: &mut Vec<F>,
+    work: &mut Vec<F>,
+) -> Catch22Result<(F, F)> {
+    if x.len() != y.len() {
+        return Err(Catch22Error::LengthMismatch(x.len(), y.len()));
+    }
+    if breaks.len() < 2 || !breaks.iter().tuple_windows().all(|(a, b)| a < b) {
+        return Err(Catch22Error::InvalidUsizeParameter {
+            name: "breaks",
+            value: breaks.len(),
+        });
+    }
+
+    let origin = breaks[0];
+    let scale = breaks[breaks.len() - 1] - origin;
+    knots.clear();
+    knots.extend(
+        breaks[1..breaks.len() - 1]
+            .iter()
+            .map(|&b| (b - origin) / scale),
+    );
+
+    let num_coefs = knots.len() + 4;
+    if x.len() < num_coefs {
+        return Err(Catch22Error::SizeUnder {
+            len: x.len(),
+            expect_minimum: num_coefs,
+        });
+    }
+
+    let rows = x.len();
+    columns.clear();
+    columns.resize(num_coefs * rows, F::zero());
+    for (i, &x) in x.iter().enumerate() {
+        for (c, b) in basis(knots, (x - origin) / scale).enumerate() {
+            columns[c * rows + i] = b;
+        }
+    }
+
+    coefs.clear();
+    coefs.extend_from_slice(y);
+    least_squares(columns, coefs, work).ok_or(Catch22Error::SizeUnder {
+        len: x.len(),
+        expect_minimum: num_coefs,
+    })?;
+    coefs.truncate(num_coefs);
+
+    Ok((origin, scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_abs_diff_eq;
+
+    #[test]
+    fn test_spline_fit_reproduces_piecewise_cubic() {
+        let x = (0..50).map(|i| i as f64).collect_vec();
+        let f = |x: f64| 0.5 - 0.2 * x + 0.01 * x * x + 0.002 * (x - 20.0).max(0.0).powi(3);
+        let y = x.iter().map(|&x| f(x)).collect_vec();
+
+        let spline = spline_fit(&x, &y, &[0.0, 20.0, 49.0]).unwrap();
+
+        for &x in &x {
+            assert_abs_diff_eq!(spline.evaluate(x), f(x), epsilon = 1e-9);
+        }
+    }
+}
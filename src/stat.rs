@@ -1,51 +1,117 @@
 use crate::primitive::Float;
 use itertools::Itertools;
 
-pub fn median(y: &[Float]) -> Float {
-    let mut y = y.to_vec();
-    y.sort_by(|x, y| x.partial_cmp(y).unwrap());
-    let mid = y.len() / 2;
-    if y.len() % 2 == 1 {
-        y[mid]
-    } else {
-        (y[mid - 1] + y[mid]) / 2.0
-    }
+pub fn covariance<F: Float>(x: &[F], y: &[F]) -> F {
+    let mean_x = x.iter().copied().sum::<F>() / F::from_usize(x.len());
+    let mean_y = y.iter().copied().sum::<F>() / F::from_usize(y.len());
+    x.iter()
+        .zip_eq(y.iter())
+        .map(|(&x, &y)| (x - mean_x) * (y - mean_y))
+        .sum::<F>()
+        / F::from_usize(x.len() - 1)
+}
+
+/// Pearson correlation of `x` and `y`, NaN if either has no variance.
+pub fn correlation<F: Float>(x: &[F], y: &[F]) -> F {
+    let mean_x = x.iter().copied().sum::<F>() / F::from_usize(x.len());
+    let mean_y = y.iter().copied().sum::<F>() / F::from_usize(y.len());
+    let (nom, denom_x, denom_y) = x.iter().zip_eq(y.iter()).fold(
+        (F::zero(), F::zero(), F::zero()),
+        |(nom, denom_x, denom_y), (&x, &y)| {
+            (
+                nom + (x - mean_x) * (y - mean_y),
+                denom_x + (x - mean_x) * (x - mean_x),
+                denom_y + (y - mean_y) * (y - mean_y),
+            )
+        },
+    );
+    nom / (denom_x * denom_y).sqrt()
 }
 
-pub fn linear_regression<'a>(
-    x: impl ExactSizeIterator<Item = &'a Float> + 'a,
-    y: impl ExactSizeIterator<Item = &'a Float> + 'a,
-) -> (Float, Float) {
+/// Pearson correlation of `values` with itself shifted by `lag`.
+pub fn autocorr_lag<F: Float>(values: &[F], lag: usize) -> F {
+    let lag = lag.min(values.len());
+    correlation(&values[..values.len() - lag], &values[lag..])
+}
+
+pub fn linear_regression<'a, F: Float>(
+    x: impl ExactSizeIterator<Item = &'a F> + 'a,
+    y: impl ExactSizeIterator<Item = &'a F> + 'a,
+) -> (F, F) {
     let len = x.len();
+    let zero = F::zero();
     let (sumx, sumx2, sumxy, sumy) = x.zip_eq(y).fold(
-        (0.0, 0.0, 0.0, 0.0),
-        |(sumx, sumx2, sumxy, sumy), (x, y)| (sumx + x, sumx2 + x * x, sumxy + x * y, sumy + y),
+        (zero, zero, zero, zero),
+        |(sumx, sumx2, sumxy, sumy), (&x, &y)| (sumx + x, sumx2 + x * x, sumxy + x * y, sumy + y),
     );
 
-    let denom = (len as Float) * sumx2 - sumx * sumx;
+    let denom = F::from_usize(len) * sumx2 - sumx * sumx;
 
-    if denom == 0.0 {
-        return (0.0, 0.0);
+    if denom == zero {
+        return (zero, zero);
     }
 
-    let a = ((len as Float) * sumxy - sumx * sumy) / denom;
+    let a = (F::from_usize(len) * sumxy - sumx * sumy) / denom;
     let b = (sumy * sumx2 - sumx * sumxy) / denom;
 
     (a, b)
 }
 
-pub fn linear_regression_and_transform<'a>(
-    x: &'a [Float],
-    y: &'a [Float],
-) -> impl Iterator<Item = Float> + 'a {
+pub fn linear_regression_and_transform<'a, F: Float>(
+    x: &'a [F],
+    y: &'a [F],
+) -> impl Iterator<Item = F> + 'a {
     let (a, b) = linear_regression(x.iter(), y.iter());
     x.iter().map(move |&x| a * x + b)
 }
 
-pub fn linear_regression_square_error(x: &[Float], y: &[Float]) -> Float {
-    let y_hat = linear_regression_and_transform(x, y).collect_vec();
+pub fn linear_regression_square_error<F: Float>(x: &[F], y: &[F]) -> F {
     y.iter()
-        .zip_eq(y_hat.iter())
+        .zip_eq(linear_regression_and_transform(x, y))
         .map(|(&y, y_hat)| (y - y_hat).powi(2))
-        .sum::<Float>()
+        .sum::<F>()
+}
+
+/// Solves the least-squares problem `min |A x - b|` by Householder QR decomposition, in
+/// place.
+///
+/// `columns` holds the columns of `A` one after the other, each as long as `b`, and `v` is
+/// working memory. The solution is left in the first elements of `b`. Returns `None` if `A`
+/// is rank deficient.
+pub fn least_squares<F: Float>(columns: &mut [F], b: &mut [F], v: &mut Vec<F>) -> Option<()> {
+    let rows = b.len();
+    let p = columns.len().checked_div(rows)?;
+
+    for k in 0..p {
+        let column = &columns[k * rows..(k + 1) * rows];
+        let norm = column[k..].iter().map(|&x| x * x).sum::<F>().sqrt();
+        if norm == F::zero() {
+            return None;
+        }
+        let alpha = if column[k] > F::zero() { -norm } else { norm };
+
+        v.clear();
+        v.extend_from_slice(&column[k..]);
+        v[0] -= alpha;
+        let v_norm2 = v.iter().map(|&x| x * x).sum::<F>();
+
+        let reflect = |target: &mut [F]| {
+            let dot = v.iter().zip(target.iter()).map(|(&v, &t)| v * t).sum::<F>();
+            let f = F::from_f64(2.0) * dot / v_norm2;
+            for (t, &v) in target.iter_mut().zip(v.iter()) {
+                *t -= f * v;
+            }
+        };
+        for column in columns[k * rows..].chunks_exact_mut(rows) {
+            reflect(&mut column[k..]);
+        }
+        reflect(&mut b[k..]);
+    }
+
+    for k in (0..p).rev() {
+        let s = (k + 1..p).map(|j| columns[j * rows + k] * b[j]).sum::<F>();
+        b[k] = (b[k] - s) / columns[k * rows + k];
+    }
+
+    Some(())
 }
@@ -0,0 +1,368 @@
+use crate::error::{Catch22Error, Catch22Result};
+use crate::primitive::Float;
+use std::collections::VecDeque;
+
+/// A feature that can be updated in constant time as samples arrive.
+///
+/// [`StreamingFeature::current`] matches the corresponding batch function applied to
+/// every sample pushed so far, exactly unless the implementation says otherwise. Features
+/// that depend on global properties of the series, such as its mean or its
+/// autocorrelation, are not streamable because a new sample can change every earlier
+/// term: this rules out
+/// [`sb_binarystats_mean_longstretch1`](crate::features::sb_binary_stats::sb_binarystats_mean_longstretch1),
+/// which binarises the series around its mean. Over a sliding window, [`RecentWindow`]
+/// recomputes any batch feature instead.
+pub trait StreamingFeature<F> {
+    fn push(&mut self, sample: F);
+
+    /// The feature of all samples pushed so far.
+    fn current(&self) -> Catch22Result<F>;
+
+    fn extend(&mut self, samples: impl IntoIterator<Item = F>)
+    where
+        Self: Sized,
+    {
+        samples.into_iter().for_each(|x| self.push(x));
+    }
+}
+
+/// Streaming [`co_trev_1_num`](crate::features::co_auto_corr::co_trev_1_num). Gives
+/// exactly the batch result.
+#[derive(Debug, Clone, Default)]
+pub struct CoTrev1Num<F> {
+    last: Option<F>,
+    len: usize,
+    sum: F,
+    has_nan: bool,
+}
+
+impl<F: Float> CoTrev1Num<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<F: Float> StreamingFeature<F> for CoTrev1Num<F> {
+    fn push(&mut self, sample: F) {
+        self.has_nan |= sample.is_nan();
+        if let Some(last) = self.last {
+            self.sum += (sample - last).powi(3);
+        }
+        self.last = Some(sample);
+        self.len += 1;
+    }
+
+    fn current(&self) -> Catch22Result<F> {
+        if self.has_nan {
+            return Ok(F::nan());
+        }
+        if self.len < 2 {
+            return Err(Catch22Error::SizeUnder {
+                len: self.len,
+                expect_minimum: 2,
+            });
+        }
+        Ok(self.sum / F::from_usize(self.len - 1))
+    }
+}
+
+/// Streaming
+/// [`sb_binarystats_diff_longstretch0`](crate::features::sb_binary_stats::sb_binarystats_diff_longstretch0).
+/// Gives exactly the batch result.
+#[derive(Debug, Clone, Default)]
+pub struct SbBinaryStatsDiffLongStretch0<F> {
+    last: Option<F>,
+    len: usize,
+    /// Longest stretch and position of the last increase, over every difference but the
+    /// most recent one, which the batch version treats specially.
+    max_stretch: i64,
+    last_increase: i64,
+    pending: Option<bool>,
+    has_nan: bool,
+}
+
+impl<F: Float> SbBinaryStatsDiffLongStretch0<F> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn close_stretch(&self, i: i64) -> i64 {
+        self.max_stretch.max(i - self.last_increase)
+    }
+}
+
+impl<F: Float> StreamingFeature<F> for SbBinaryStatsDiffLongStretch0<F> {
+    fn push(&mut self, sample: F) {
+        self.has_nan |= sample.is_nan();
+        if let Some(last) = self.last {
+            if let Some(increase) = self.pending {
+                if increase {
+                    let i = self.len as i64 - 2;
+                    self.max_stretch = self.close_stretch(i);
+                    self.last_increase = i;
+                }
+            }
+            self.pending = Some(!(sample - last).is_sign_negative());
+        }
+        self.last = Some(sample);
+        self.len += 1;
+    }
+
+    fn current(&self) -> Catch22Result<F> {
+        if self.len == 0 {
+            return Err(Catch22Error::EmptyInput);
+        }
+        if self.len == 1 {
+            return Err(Catch22Error::SizeUnder {
+                len: self.len,
+                expect_minimum: 2,
+            });
+        }
+        if self.has_nan {
+            return Ok(F::nan());
+        }
+        Ok(F::from_f64(self.close_stretch(self.len as i64 - 2) as f64))
+    }
+}
+
+/// Streaming
+/// [`fc_localsimple_mean3_stderr`](crate::features::fc_local_simple::fc_localsimple_mean3_stderr)
+/// and its variants with other training lengths.
+///
+/// Agrees with the batch result up to rounding only: the residuals are exactly those of
+/// the batch version, but the batch standard deviation is taken around the mean of all
+/// residuals, which moves with every sample, so it is accumulated here with Welford's
+/// algorithm instead.
+#[derive(Debug, Clone)]
+pub struct FcLocalSimpleMeanStderr<F> {
+    train_length: usize,
+    window: VecDeque<F>,
+    len: usize,
+    count: usize,
+    mean: F,
+    m2: F,
+    has_nan: bool,
+}
+
+impl<F: Float> FcLocalSimpleMeanStderr<F> {
+    pub fn new(train_length: usize) -> Catch22Result<Self> {
+        if train_length == 0 {
+            return Err(Catch22Error::InvalidUsizeParameter {
+                name: "train_length",
+                value: train_length,
+            });
+        }
+        Ok(Self {
+            train_length,
+            window: VecDeque::new(),
+            len: 0,
+            count: 0,
+            mean: F::zero(),
+            m2: F::zero(),
+            has_nan: false,
+        })
+    }
+
+    pub fn mean3() -> Self {
+        Self::new(3).unwrap()
+    }
+}
+
+impl<F: Float> StreamingFeature<F> for FcLocalSimpleMeanStderr<F> {
+    fn push(&mut self, sample: F) {
+        self.has_nan |= sample.is_nan();
+        self.len += 1;
+
+        if self.window.len() == self.train_length {
+            let train_mean =
+                self.window.iter().copied().sum::<F>() / F::from_usize(self.train_length);
+            let residual = sample - train_mean;
+
+            self.count += 1;
+            let delta = residual - self.mean;
+            self.mean += delta / F::from_usize(self.count);
+            self.m2 += delta * (residual - self.mean);
+
+            self.window.pop_front();
+        }
+        self.window.push_back(sample);
+    }
+
+    fn current(&self) -> Catch22Result<F> {
+        if self.has_nan {
+            return Ok(F::nan());
+        }
+        if self.count < 2 {
+            return Err(Catch22Error::SizeUnder {
+                len: self.len,
+                expect_minimum: self.train_length.saturating_add(2),
+            });
+        }
+        Ok((self.m2 / F::from_usize(self.count - 1)).sqrt())
+    }
+}
+
+/// The most recent samples of a stream, to recompute batch features over a sliding
+/// window.
+///
+/// Unlike a [`StreamingFeature`], [`RecentWindow::compute`] runs the batch function on
+/// the whole window, in time proportional to its length, and so gives exactly its
+/// result for any feature. Pushing a sample takes amortised constant time.
+#[derive(Debug, Clone)]
+pub struct RecentWindow<F> {
+    length: usize,
+    buffer: Vec<F>,
+}
+
+impl<F: Float> RecentWindow<F> {
+    pub fn new(length: usize) -> Catch22Result<Self> {
+        if length == 0 {
+            return Err(Catch22Error::InvalidUsizeParameter {
+                name: "length",
+                value: length,
+            });
+        }
+        Ok(Self {
+            length,
+            buffer: vec![],
+        })
+    }
+
+    pub fn push(&mut self, sample: F) {
+        // up to twice the window is kept, so that samples are moved once per window
+        if self.buffer.len() >= self.length.saturating_mul(2) {
+            self.buffer.drain(..self.buffer.len() - self.length);
+        }
+        self.buffer.push(sample);
+    }
+
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = F>) {
+        samples.into_iter().for_each(|x| self.push(x));
+    }
+
+    /// The last `length` samples pushed, or all of them while fewer have been pushed.
+    pub fn values(&self) -> &[F] {
+        &self.buffer[self.buffer.len().saturating_sub(self.length)..]
+    }
+
+    /// The batch `feature` of [`RecentWindow::values`].
+    pub fn compute(&self, feature: impl FnOnce(&[F]) -> Catch22Result<F>) -> Catch22Result<F> {
+        feature(self.values())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::features::co_auto_corr::co_trev_1_num;
+    use crate::features::fc_local_simple::fc_localsimple_mean3_stderr;
+    use crate::features::sb_binary_stats::{
+        sb_binarystats_diff_longstretch0, sb_binarystats_mean_longstretch1,
+    };
+    use crate::test_utility::{arbitrary_series, load_test_data};
+    use approx::assert_relative_eq;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_streaming_same_as_batch_at_every_length() {
+        let numbers = load_test_data::<f64>();
+        let mut trev = CoTrev1Num::new();
+        let mut stretch = SbBinaryStatsDiffLongStretch0::new();
+        let mut stderr = FcLocalSimpleMeanStderr::mean3();
+
+        for (i, &x) in numbers.iter().enumerate() {
+            trev.push(x);
+            stretch.push(x);
+            stderr.push(x);
+
+            let seen = &numbers[..=i];
+            if seen.len() >= 2 {
+                assert_eq!(trev.current().unwrap(), co_trev_1_num(seen).unwrap());
+                assert_eq!(
+                    stretch.current().unwrap(),
+                    sb_binarystats_diff_longstretch0(seen).unwrap()
+                );
+            } else {
+                assert!(trev.current().is_err());
+                assert!(stretch.current().is_err());
+            }
+            if seen.len() >= 5 {
+                assert_relative_eq!(
+                    stderr.current().unwrap(),
+                    fc_localsimple_mean3_stderr(seen).unwrap(),
+                    max_relative = 1e-12
+                );
+            } else {
+                assert!(stderr.current().is_err());
+            }
+        }
+        assert_eq!(stretch.current().unwrap(), 83.0);
+    }
+
+    #[test]
+    fn test_recent_window_same_as_batch_on_every_window() {
+        let numbers = load_test_data::<f64>();
+        let mut series = numbers
+            .iter()
+            .cycle()
+            .take(2000)
+            .enumerate()
+            .map(|(i, &x)| x + 1e-3 * i as f64)
+            .collect::<Vec<_>>();
+        series[700] = f64::NAN;
+        let features = [
+            co_trev_1_num as fn(&[f64]) -> _,
+            sb_binarystats_diff_longstretch0,
+            sb_binarystats_mean_longstretch1,
+            fc_localsimple_mean3_stderr,
+        ];
+        let mut recent = RecentWindow::new(50).unwrap();
+
+        for (i, &x) in series.iter().enumerate() {
+            recent.push(x);
+
+            let window = &series[(i + 1).saturating_sub(50)..=i];
+            assert!(recent
+                .values()
+                .iter()
+                .zip(window.iter())
+                .all(|(a, b)| a.to_bits() == b.to_bits()));
+            assert_eq!(recent.values().len(), window.len());
+            for feature in features {
+                match (recent.compute(feature), feature(window)) {
+                    (Ok(actual), Ok(expected)) => {
+                        assert!(actual == expected || (actual.is_nan() && expected.is_nan()))
+                    }
+                    (actual, expected) => assert_eq!(actual, expected),
+                }
+            }
+        }
+        assert!(RecentWindow::<f64>::new(0).is_err());
+    }
+
+    #[test]
+    fn test_streaming_propagates_nan() {
+        let mut trev = CoTrev1Num::new();
+        trev.extend([1.0, f64::NAN, 2.0]);
+
+        assert!(trev.current().unwrap().is_nan());
+    }
+
+    proptest! {
+        #[test]
+        fn test_streaming_never_panics(
+            values in arbitrary_series(),
+            train_length in prop_oneof![0..6usize, Just(usize::MAX), any::<usize>()],
+            length in prop_oneof![0..6usize, Just(usize::MAX), any::<usize>()],
+        ) {
+            if let Ok(mut stderr) = FcLocalSimpleMeanStderr::new(train_length) {
+                stderr.extend(values.iter().copied());
+                let _ = stderr.current();
+            }
+            if let Ok(mut recent) = RecentWindow::new(length) {
+                recent.extend(values.iter().copied());
+                let _ = recent.compute(co_trev_1_num);
+            }
+        }
+    }
+}
@@ -4,13 +4,25 @@ use std::fs::File;
 use std::io::{BufRead, BufReader};
 
 #[allow(dead_code)]
-pub fn load_test_data() -> Vec<Float> {
+pub fn load_test_data<F: Float>() -> Vec<F> {
     let input_file = File::open("./test_data/test.txt").unwrap();
 
     let buf_reader = BufReader::new(input_file);
 
     buf_reader
         .lines()
-        .map(|l| l.unwrap().parse::<f64>().unwrap())
+        .map(|l| F::from_f64(l.unwrap().parse::<f64>().unwrap()))
         .collect_vec()
 }
+
+/// Series of up to 80 values mixing arbitrary floats (including NaN, ±Inf and extreme
+/// magnitudes) with small integers, so that ties and constant runs are common.
+#[cfg(test)]
+pub fn arbitrary_series() -> impl proptest::strategy::Strategy<Value = Vec<f64>> {
+    use proptest::prelude::*;
+
+    prop::collection::vec(
+        prop_oneof![any::<f64>(), (-3i8..3).prop_map(f64::from), -1e3..1e3f64],
+        0..80,
+    )
+}
@@ -1,7 +1,9 @@
+use crate::error::{Catch22Error, Catch22Result};
+use crate::hist_count::MAX_BINS;
 use crate::primitive::Float;
 use itertools::Itertools;
-
-use unwrap_ord::UnwrapOrd;
+use std::borrow::Cow;
+use std::cmp::Ordering;
 
 pub fn nextpow2(x: usize) -> Option<usize> {
     let mut y = 1;
@@ -11,19 +13,26 @@ pub fn nextpow2(x: usize) -> Option<usize> {
     Some(y)
 }
 
-pub fn any_nan(x: &[Float]) -> bool {
+/// Total order on floats that sorts NaN after every other value, for sorting series
+/// that may contain NaN without panicking.
+pub fn total_cmp<F: Float>(a: &F, b: &F) -> Ordering {
+    a.partial_cmp(b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+pub fn any_nan<F: Float>(x: &[F]) -> bool {
     x.iter().any(|&x| x.is_nan())
 }
 
-pub fn mean(x: &[Float]) -> Option<Float> {
+pub fn mean<F: Float>(x: &[F]) -> Option<F> {
     if x.is_empty() {
         return None;
     }
-    Some(x.iter().sum::<Float>() / x.len() as Float)
+    Some(x.iter().copied().sum::<F>() / F::from_usize(x.len()))
 }
 
-pub fn mean_iter(values: impl Iterator<Item = Float>) -> Option<Float> {
-    let mut sum = 0.0;
+pub fn mean_iter<F: Float>(values: impl Iterator<Item = F>) -> Option<F> {
+    let mut sum = F::zero();
     let mut len = 0;
     for value in values {
         sum += value;
@@ -32,48 +41,93 @@ pub fn mean_iter(values: impl Iterator<Item = Float>) -> Option<Float> {
     if len == 0 {
         None
     } else {
-        Some(sum / len as Float)
+        Some(sum / F::from_usize(len))
     }
 }
 
-pub fn stddev(values: &[Float]) -> Option<Float> {
+pub fn stddev<F: Float>(values: &[F]) -> Option<F> {
     if values.len() < 2 {
         return None;
     }
     let m = mean(values)?;
     Some(
-        (values.iter().map(|&x| (x - m).powi(2)).sum::<Float>() / (values.len() - 1) as Float)
+        (values.iter().map(|&x| (x - m).powi(2)).sum::<F>() / F::from_usize(values.len() - 1))
             .sqrt(),
     )
 }
 
-pub fn linspace(start: Float, end: Float, num_groups: usize) -> impl Iterator<Item = Float> {
-    let step_size = (end - start) / (num_groups as Float - 1.0);
-    (0..num_groups).map(move |i| start + step_size * (i as Float))
+/// Standardises `values` to zero mean and unit (sample) standard deviation.
+///
+/// A constant series has zero deviation and yields NaN, as in the reference implementation.
+pub fn zscore<F: Float>(values: &[F]) -> Option<Vec<F>> {
+    let mut output = vec![];
+    zscore_into(values, &mut output)?;
+    Some(output)
+}
+
+/// Same as [`zscore`], writing into `output` so that its allocation can be reused.
+pub fn zscore_into<F: Float>(values: &[F], output: &mut Vec<F>) -> Option<()> {
+    let m = mean(values)?;
+    let s = stddev(values)?;
+    output.clear();
+    output.extend(values.iter().map(|&x| (x - m) / s));
+    Some(())
 }
 
-pub fn quantile(values: Vec<Float>, quant: Float) -> Float {
-    let size = values.len();
+pub fn linspace<F: Float>(start: F, end: F, num_groups: usize) -> impl Iterator<Item = F> {
+    let step_size = (end - start) / (F::from_usize(num_groups) - F::one());
+    (0..num_groups).map(move |i| start + step_size * F::from_usize(i))
+}
 
-    let values = values
-        .iter()
-        .copied()
-        .sorted_by_key(|&x| UnwrapOrd(x))
-        .collect_vec();
-
-    let q = 0.5 / size as Float;
-    if quant < q {
-        return values.first().cloned().unwrap(); // min value
-    } else if quant > (1.0 - q) {
-        return values.last().cloned().unwrap(); // max value
+pub fn quantile<F: Float>(values: Vec<F>, quant: F) -> F {
+    Quantiles::new(&values).quantile(quant)
+}
+
+/// Sorted view of a series, answering many [`quantile`] queries with a single sort.
+pub struct Quantiles<'a, F: Clone> {
+    sorted: Cow<'a, [F]>,
+}
+
+impl<'a, F: Float> Quantiles<'a, F> {
+    pub fn new(values: &[F]) -> Self {
+        let sorted = values.iter().copied().sorted_by(total_cmp).collect_vec();
+        Self {
+            sorted: Cow::Owned(sorted),
+        }
+    }
+
+    /// Uses `sorted` as is; it must hold the values in ascending order.
+    pub fn from_sorted(sorted: &'a [F]) -> Self {
+        Self {
+            sorted: Cow::Borrowed(sorted),
+        }
+    }
+
+    pub fn sorted(&self) -> &[F] {
+        &self.sorted
     }
 
-    let quant_idx = size as Float * quant - 0.5;
-    let idx_left = quant_idx.floor() as usize;
-    let idx_right = quant_idx.ceil() as usize;
-    values[idx_left]
-        + (quant_idx - idx_left as Float) * (values[idx_right] - values[idx_left])
-            / (idx_right - idx_left) as Float
+    pub fn quantile(&self, quant: F) -> F {
+        let values = self.sorted();
+        let size = values.len();
+        if size == 0 || quant.is_nan() {
+            return F::nan();
+        }
+
+        let q = F::from_f64(0.5) / F::from_usize(size);
+        if quant < q {
+            return values.first().cloned().unwrap(); // min value
+        } else if quant > (F::one() - q) {
+            return values.last().cloned().unwrap(); // max value
+        }
+
+        let quant_idx = F::from_usize(size) * quant - F::from_f64(0.5);
+        let idx_left = quant_idx.floor().as_usize().min(size - 1);
+        let idx_right = quant_idx.ceil().as_usize().min(size - 1);
+        values[idx_left]
+            + (quant_idx - F::from_usize(idx_left)) * (values[idx_right] - values[idx_left])
+                / F::from_usize(idx_right - idx_left)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -84,37 +138,137 @@ impl Alphabet {
         Self(value)
     }
 
-    pub fn from_one_indexed(value: usize) -> Self {
-        assert!(value > 0);
-        Self(value - 1)
+    pub fn from_one_indexed(value: usize) -> Option<Self> {
+        value.checked_sub(1).map(Self)
+    }
+
+    pub fn zero_indexed(&self) -> usize {
+        self.0
     }
 }
 
-pub fn sb_coarsegrain(values: &[Float], num_groups: usize) -> Vec<Alphabet> {
-    let mut th = linspace(0.0, 1.0, num_groups + 1)
-        .map(|x| quantile(values.to_vec(), x))
-        .collect_vec();
-    th[0] -= 1.0;
+/// Labels each value with the group of its quantile, out of `num_groups` groups of equal
+/// probability. At most [`MAX_BINS`] groups are allowed.
+pub fn sb_coarsegrain<F: Float>(values: &[F], num_groups: usize) -> Catch22Result<Vec<Alphabet>> {
+    sb_coarsegrain_with(values, &Quantiles::new(values), num_groups)
+}
 
-    values
-        .iter()
-        .map(|&value| {
-            th.windows(2)
-                .enumerate()
-                .find_map(|(i, window_values)| {
-                    (window_values[0] < value && value <= window_values[1])
-                        .then_some(Alphabet::from_zero_indexed(i))
-                })
-                .unwrap()
-        })
-        .collect_vec()
+/// Same as [`sb_coarsegrain`] with the quantiles of `values` already prepared.
+pub fn sb_coarsegrain_with<F: Float>(
+    values: &[F],
+    quantiles: &Quantiles<F>,
+    num_groups: usize,
+) -> Catch22Result<Vec<Alphabet>> {
+    let mut labels = vec![];
+    sb_coarsegrain_into(
+        values,
+        quantiles,
+        num_groups,
+        &mut vec![],
+        &mut vec![],
+        &mut labels,
+    )?;
+    Ok(labels
+        .into_iter()
+        .map(Alphabet::from_zero_indexed)
+        .collect_vec())
 }
 
-pub fn entropy(values: &[Float]) -> Float {
+/// Same as [`sb_coarsegrain_with`], writing the zero-indexed labels into `labels` and using
+/// `th` and `groups` as working memory.
+///
+/// A value gets the first group `i` with `th[i] < value <= th[i + 1]`. Groups with a NaN
+/// threshold never match, and the bounds of the others are non-decreasing, so the group is
+/// found by binary search.
+pub(crate) fn sb_coarsegrain_into<F: Float>(
+    values: &[F],
+    quantiles: &Quantiles<F>,
+    num_groups: usize,
+    th: &mut Vec<F>,
+    groups: &mut Vec<usize>,
+    labels: &mut Vec<usize>,
+) -> Catch22Result<()> {
+    if num_groups > MAX_BINS {
+        return Err(Catch22Error::InvalidUsizeParameter {
+            name: "num_groups",
+            value: num_groups,
+        });
+    }
+
+    th.clear();
+    th.extend(linspace(F::zero(), F::one(), num_groups + 1).map(|x| quantiles.quantile(x)));
+    th[0] -= F::one();
+
+    groups.clear();
+    groups.extend((0..num_groups).filter(|&i| !th[i].is_nan() && !th[i + 1].is_nan()));
+
+    labels.clear();
+    labels.extend(values.iter().map(|&value| {
+        let k = groups.partition_point(|&i| th[i + 1] < value);
+        groups
+            .get(k)
+            .copied()
+            .filter(|&i| th[i] < value)
+            // only reachable for values the thresholds cannot order, such as NaN
+            .unwrap_or(num_groups.max(1) - 1)
+    }));
+    Ok(())
+}
+
+#[allow(clippy::filter_map_bool_then)]
+pub fn entropy<F: Float>(values: &[F]) -> F {
     let f = values
         .iter()
-        .filter_map(|&x| (x > 0.0).then(|| x * x.ln()))
-        .sum::<Float>();
+        .filter_map(|&x| (x > F::zero()).then(|| x * x.ln()))
+        .sum::<F>();
 
     -f
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utility::arbitrary_series;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn test_utilities_never_panic(
+            values in arbitrary_series(),
+            groups in prop_oneof![0..6usize, Just(MAX_BINS + 1), Just(usize::MAX), any::<usize>()],
+            q: f64,
+        ) {
+            let _ = quantile(values.clone(), q);
+            if let Ok(labels) = sb_coarsegrain(&values, groups) {
+                prop_assert_eq!(labels.len(), values.len());
+            }
+            let _ = zscore(&values);
+            let _ = entropy(&values);
+        }
+
+        #[test]
+        fn test_sb_coarsegrain_same_as_linear_scan(values in arbitrary_series(), groups in 1..6usize) {
+            let values = values.into_iter().filter(|x| x.abs() < 1e300).collect_vec();
+            let quantiles = Quantiles::new(&values);
+            let mut th = linspace(0.0, 1.0, groups + 1)
+                .map(|x| quantiles.quantile(x))
+                .collect_vec();
+            th[0] -= 1.0;
+            let expected = values
+                .iter()
+                .map(|&value| {
+                    th.windows(2)
+                        .position(|w| w[0] < value && value <= w[1])
+                        .unwrap_or(groups - 1)
+                })
+                .collect_vec();
+
+            let labels = sb_coarsegrain(&values, groups)
+                .unwrap()
+                .iter()
+                .map(Alphabet::zero_indexed)
+                .collect_vec();
+            prop_assert_eq!(labels, expected);
+        }
+    }
+}
@@ -0,0 +1,311 @@
+use crate::catch22::{catch22_extend, extend_row_or_nan, Catch22Options};
+use crate::context::BufferPool;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::fft::FftPlanner;
+use crate::primitive::Float;
+use itertools::Itertools;
+
+/// Which sample of a window its time index refers to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowAlignment {
+    /// The first sample of the window.
+    Start,
+    /// The middle sample of the window, rounding down for even lengths.
+    Center,
+    /// The last sample of the window.
+    End,
+}
+
+/// What to do with the trailing samples that are not covered by any full window.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartialWindow {
+    /// Ignore them.
+    Drop,
+    /// Compute the features of a final, shorter window starting one hop after the last
+    /// full window.
+    Shorter,
+    /// Fail with [`Catch22Error::SizeUnder`].
+    Error,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct WindowOptions {
+    /// Number of samples per window.
+    pub length: usize,
+    /// Number of samples between the starts of consecutive windows.
+    pub hop: usize,
+    pub alignment: WindowAlignment,
+    pub partial: PartialWindow,
+    pub catch22: Catch22Options,
+}
+
+impl WindowOptions {
+    /// Windows of `length` samples every `hop` samples, indexed by their first sample,
+    /// dropping the trailing partial window.
+    pub fn new(length: usize, hop: usize) -> Self {
+        Self {
+            length,
+            hop,
+            alignment: WindowAlignment::Start,
+            partial: PartialWindow::Drop,
+            catch22: Catch22Options::default(),
+        }
+    }
+}
+
+/// Time indexed feature matrix, one row per window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WindowedOutput<F> {
+    pub names: Vec<&'static str>,
+    pub short_names: Vec<&'static str>,
+    /// Sample index of each window, according to [`WindowOptions::alignment`].
+    pub index: Vec<usize>,
+    /// Row major values, `names.len()` per window. The rows of failed windows are NaN.
+    pub values: Vec<F>,
+    /// The windows whose extraction failed, by row, in ascending order.
+    pub errors: Vec<(usize, Catch22Error)>,
+}
+
+impl<F: Float> WindowedOutput<F> {
+    /// Number of windows (rows).
+    pub fn len(&self) -> usize {
+        self.index.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.index.is_empty()
+    }
+
+    /// Features of the `i`-th window.
+    pub fn row(&self, i: usize) -> &[F] {
+        &self.values[i * self.names.len()..(i + 1) * self.names.len()]
+    }
+}
+
+/// Computes catch22 features over sliding windows, reusing its FFT tables and buffers
+/// across windows and across calls.
+pub struct WindowExtractor<F> {
+    options: WindowOptions,
+    planner: FftPlanner<F>,
+    pool: BufferPool<F>,
+}
+
+impl<F: Float> WindowExtractor<F> {
+    pub fn new(options: WindowOptions) -> Catch22Result<Self> {
+        if options.length == 0 {
+            return Err(Catch22Error::InvalidUsizeParameter {
+                name: "length",
+                value: options.length,
+            });
+        }
+        if options.hop == 0 {
+            return Err(Catch22Error::InvalidUsizeParameter {
+                name: "hop",
+                value: options.hop,
+            });
+        }
+
+        Ok(Self {
+            options,
+            planner: FftPlanner::new(),
+            pool: BufferPool::default(),
+        })
+    }
+
+    /// The `(start, end)` sample ranges of the windows of a series of `len` samples.
+    pub fn windows(&self, len: usize) -> Catch22Result<Vec<(usize, usize)>> {
+        let WindowOptions { length, hop, .. } = self.options;
+
+        let mut windows = match len.checked_sub(length) {
+            Some(last_start) => (0..=last_start)
+                .step_by(hop)
+                .map(|start| (start, start + length))
+                .collect_vec(),
+            None => vec![],
+        };
+
+        let covered = windows.last().map_or(0, |&(_, end)| end);
+        if covered < len {
+            let start = match windows.last() {
+                Some(&(start, _)) => start.checked_add(hop),
+                None => Some(0),
+            };
+            if let Some(start) = start.filter(|&start| start < len) {
+                match self.options.partial {
+                    PartialWindow::Drop => {}
+                    PartialWindow::Shorter => windows.push((start, len)),
+                    PartialWindow::Error => {
+                        return Err(Catch22Error::SizeUnder {
+                            len: len - start,
+                            expect_minimum: length,
+                        })
+                    }
+                }
+            }
+        }
+
+        Ok(windows)
+    }
+
+    /// Computes the features of every window of `values`.
+    ///
+    /// A window that fails, such as a trailing window too short for some features, does
+    /// not abort the extraction; its row is NaN and its error is reported in
+    /// [`WindowedOutput::errors`].
+    pub fn extract(&mut self, values: &[F]) -> Catch22Result<WindowedOutput<F>> {
+        let windows = self.windows(values.len())?;
+        let features = self.options.catch22.features::<F>();
+
+        let mut output = Vec::with_capacity(windows.len() * features.len());
+        let mut errors = vec![];
+        for (i, &(start, end)) in windows.iter().enumerate() {
+            extend_row_or_nan(&mut output, features.len(), &mut errors, i, |output| {
+                catch22_extend(
+                    &values[start..end],
+                    self.options.catch22,
+                    &mut self.planner,
+                    &mut self.pool,
+                    output,
+                )
+            });
+        }
+
+        Ok(WindowedOutput {
+            names: features.iter().map(|f| f.name).collect_vec(),
+            short_names: features.iter().map(|f| f.short_name).collect_vec(),
+            index: windows
+                .iter()
+                .map(|&(start, end)| match self.options.alignment {
+                    WindowAlignment::Start => start,
+                    WindowAlignment::Center => start + (end - start - 1) / 2,
+                    WindowAlignment::End => end - 1,
+                })
+                .collect_vec(),
+            values: output,
+            errors,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catch22::catch22_all;
+    use crate::test_utility::{arbitrary_series, load_test_data};
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_window_extractor_same_as_per_window() {
+        let numbers = load_test_data::<f64>();
+        let options = WindowOptions {
+            alignment: WindowAlignment::Center,
+            partial: PartialWindow::Shorter,
+            ..WindowOptions::new(100, 40)
+        };
+        let mut extractor = WindowExtractor::new(options).unwrap();
+
+        let output = extractor.extract(&numbers).unwrap();
+
+        // full windows start at 0, 40, 80, 120, 160 and cover up to 260 of 270 samples
+        assert_eq!(output.index, [49, 89, 129, 169, 209, 234]);
+        for (i, start) in [0, 40, 80, 120, 160, 200].into_iter().enumerate() {
+            let end = (start + 100).min(numbers.len());
+            assert_eq!(
+                output.row(i),
+                catch22_all(&numbers[start..end]).unwrap().values
+            );
+        }
+        assert!(output.errors.is_empty());
+        assert_eq!(extractor.extract(&numbers).unwrap(), output);
+    }
+
+    #[test]
+    fn test_window_failing_trailing_window_is_nan() {
+        let numbers = load_test_data::<f64>();
+        let options = WindowOptions {
+            partial: PartialWindow::Shorter,
+            ..WindowOptions::new(100, 100)
+        };
+        let mut extractor = WindowExtractor::new(options).unwrap();
+
+        let output = extractor.extract(&numbers[..101]).unwrap();
+
+        assert_eq!(output.index, [0, 100]);
+        assert_eq!(
+            output.errors,
+            [(
+                1,
+                Catch22Error::SizeUnder {
+                    len: 1,
+                    expect_minimum: 2
+                }
+            )]
+        );
+        assert_eq!(output.row(0), catch22_all(&numbers[..100]).unwrap().values);
+        assert!(output.row(1).iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn test_window_partial_policy() {
+        let windows = |partial, len| {
+            WindowExtractor::<f64>::new(WindowOptions {
+                partial,
+                ..WindowOptions::new(4, 2)
+            })
+            .unwrap()
+            .windows(len)
+        };
+
+        assert_eq!(
+            windows(PartialWindow::Drop, 11).unwrap(),
+            [(0, 4), (2, 6), (4, 8), (6, 10)]
+        );
+        assert_eq!(
+            windows(PartialWindow::Shorter, 11).unwrap().last(),
+            Some(&(8, 11))
+        );
+        assert!(windows(PartialWindow::Error, 11).is_err());
+        // every sample is covered by a full window, so there is no partial window
+        assert_eq!(windows(PartialWindow::Error, 10).unwrap().len(), 4);
+        assert_eq!(windows(PartialWindow::Shorter, 3).unwrap(), [(0, 3)]);
+        assert!(windows(PartialWindow::Drop, 3).unwrap().is_empty());
+        assert!(WindowExtractor::<f64>::new(WindowOptions::new(4, 0)).is_err());
+
+        // hops and lengths near the top of the range must not overflow
+        for partial in [
+            PartialWindow::Drop,
+            PartialWindow::Shorter,
+            PartialWindow::Error,
+        ] {
+            let huge = |length, hop| {
+                WindowExtractor::<f64>::new(WindowOptions {
+                    partial,
+                    ..WindowOptions::new(length, hop)
+                })
+                .unwrap()
+                .windows(11)
+            };
+            assert_eq!(huge(4, usize::MAX / 2).unwrap()[0], (0, 4));
+            assert_eq!(huge(4, usize::MAX).unwrap().len(), 1);
+            let _ = huge(usize::MAX, usize::MAX);
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_window_extractor_never_panics(
+            values in arbitrary_series(),
+            length in prop_oneof![0..6usize, Just(usize::MAX), any::<usize>()],
+            hop in prop_oneof![0..6usize, Just(usize::MAX), any::<usize>()],
+            shorter: bool,
+        ) {
+            let options = WindowOptions {
+                partial: if shorter { PartialWindow::Shorter } else { PartialWindow::Error },
+                ..WindowOptions::new(length, hop)
+            };
+            if let Ok(mut extractor) = WindowExtractor::new(options) {
+                let _ = extractor.extract(&values);
+            }
+        }
+    }
+}
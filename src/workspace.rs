@@ -0,0 +1,107 @@
+use crate::catch22::{catch22_extend, Catch22Options};
+use crate::context::BufferPool;
+use crate::error::{Catch22Error, Catch22Result};
+use crate::fft::{next_fast_len, FftPlanner};
+use crate::primitive::Float;
+use crate::utility::nextpow2;
+
+/// Working memory for extracting the features of many series of at most `max_len` samples
+/// without allocating.
+///
+/// Everything the features need is allocated by [`Workspace::new`], which plans the FFT
+/// of every length a series of up to `max_len` samples can need and runs a warm-up
+/// extraction to size the buffers. Series of any length up to `max_len` are then
+/// extracted without any heap allocation.
+///
+/// The transforms are all 5-smooth or powers of two, so there are few of them, but their
+/// tables take memory proportional to their length: about 30 times `max_len` complex
+/// values for a hundred samples, 50 for a thousand and 130 for a hundred thousand, on
+/// top of the buffers.
+pub struct Workspace<F> {
+    max_len: usize,
+    planner: FftPlanner<F>,
+    pool: BufferPool<F>,
+    output: Vec<F>,
+}
+
+impl<F: Float> Workspace<F> {
+    pub fn new(max_len: usize) -> Catch22Result<Self> {
+        // the longest buffers are the spline design matrix (five columns of the length
+        // of the series) and the zero padded autocorrelation
+        let padded = next_fast_len(max_len)
+            .and_then(|n| n.checked_mul(2))
+            .ok_or(Catch22Error::SizeOver(max_len))?;
+        let capacity = max_len
+            .checked_mul(5)
+            .ok_or(Catch22Error::SizeOver(max_len))?
+            .max(padded);
+
+        let mut workspace = Self {
+            max_len,
+            planner: FftPlanner::new(),
+            pool: BufferPool::with_capacity(capacity),
+            output: Vec::with_capacity(24),
+        };
+
+        // the autocorrelation pads to twice the next fast length and the spectrum to the
+        // next power of two; both take few distinct values, each planned once
+        let mut zeros = vec![];
+        let mut spectrum = vec![];
+        let mut plan = |n: usize| {
+            zeros.resize(n, F::zero());
+            workspace.planner.real_fft(&zeros, &mut spectrum);
+        };
+        let mut len = 1;
+        while len <= max_len {
+            let fast = next_fast_len(len).ok_or(Catch22Error::SizeOver(max_len))?;
+            plan(2 * fast);
+            len = fast + 1;
+        }
+        let mut len = 1;
+        while len <= max_len {
+            let pow2 = nextpow2(len).ok_or(Catch22Error::SizeOver(max_len))?;
+            plan(pow2);
+            len = pow2 + 1;
+        }
+
+        // a pseudo-random series takes the longest path through every feature
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let warm_up: Vec<F> = (0..max_len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                F::from_f64((state >> 11) as f64 / (1u64 << 53) as f64)
+            })
+            .collect();
+        let options = Catch22Options {
+            catch24: true,
+            ..Catch22Options::default()
+        };
+        // short series fail some features, which is fine for the warm-up
+        let _ = workspace.extract(&warm_up, options);
+
+        Ok(workspace)
+    }
+
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Computes the features of `values` in the order of [`Catch22Options::features`],
+    /// returning a view valid until the next extraction.
+    pub fn extract(&mut self, values: &[F], options: Catch22Options) -> Catch22Result<&[F]> {
+        if values.len() > self.max_len {
+            return Err(Catch22Error::SizeOver(values.len()));
+        }
+        self.output.clear();
+        catch22_extend(
+            values,
+            options,
+            &mut self.planner,
+            &mut self.pool,
+            &mut self.output,
+        )?;
+        Ok(&self.output)
+    }
+}
@@ -0,0 +1,152 @@
+use catch22_rs::missing::MissingPolicy;
+use catch22_rs::{catch22_all_with, Catch22Options, Workspace};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs;
+
+/// Counts the allocations of the threads that enabled counting.
+struct CountingAllocator;
+
+thread_local! {
+    static COUNTING: Cell<bool> = const { Cell::new(false) };
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn record() {
+    if COUNTING.with(Cell::get) {
+        ALLOCATIONS.with(|a| a.set(a.get() + 1));
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record();
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record();
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Number of allocations made by `f` on this thread.
+fn count_allocations(f: impl FnOnce()) -> usize {
+    ALLOCATIONS.with(|a| a.set(0));
+    COUNTING.with(|c| c.set(true));
+    f();
+    COUNTING.with(|c| c.set(false));
+    ALLOCATIONS.with(Cell::get)
+}
+
+fn load_test_data() -> Vec<f64> {
+    fs::read_to_string("./test_data/test.txt")
+        .unwrap()
+        .lines()
+        .map(|l| l.parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn test_workspace_does_not_allocate_after_warm_up() {
+    let numbers = load_test_data();
+    let len = numbers.len();
+
+    let mut with_nan = numbers.clone();
+    with_nan[len / 3] = f64::NAN;
+    let series = [
+        numbers.clone(),
+        numbers.iter().map(|x| x * 1e3 - 7.0).collect(),
+        numbers.iter().rev().copied().collect(),
+        (0..len).map(|i| (i as f64 * 0.3).sin()).collect(),
+        vec![1.0; len],
+        with_nan,
+    ];
+    let options = [
+        Catch22Options::default(),
+        Catch22Options {
+            catch24: true,
+            normalize: false,
+            ..Catch22Options::default()
+        },
+        Catch22Options {
+            missing: MissingPolicy::Interpolate,
+            ..Catch22Options::default()
+        },
+    ];
+
+    // the counter sees the allocations of the one-off API
+    assert!(count_allocations(|| drop(catch22_all_with(&numbers, Catch22Options::default()))) > 0);
+
+    let mut workspace = Workspace::new(len).unwrap();
+    for values in series.iter() {
+        for &options in options.iter() {
+            let expected = catch22_all_with(values, options).map(|o| o.values);
+            let allocations = count_allocations(|| {
+                let _ = workspace.extract(values, options);
+            });
+            assert_eq!(allocations, 0);
+
+            let actual = workspace.extract(values, options).map(<[f64]>::to_vec);
+            match (actual, expected) {
+                (Ok(actual), Ok(expected)) => {
+                    assert_eq!(actual.len(), expected.len());
+                    for (a, e) in actual.iter().zip(expected.iter()) {
+                        assert!(a == e || (a.is_nan() && e.is_nan()));
+                    }
+                }
+                (actual, expected) => assert_eq!(actual.is_err(), expected.is_err()),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_workspace_rejects_longer_series() {
+    let mut workspace = Workspace::<f64>::new(10).unwrap();
+    assert_eq!(workspace.max_len(), 10);
+    assert!(workspace
+        .extract(&[0.0; 11], Catch22Options::default())
+        .is_err());
+}
+
+#[test]
+fn test_workspace_does_not_allocate_for_shorter_series() {
+    let numbers = load_test_data();
+    let options = Catch22Options {
+        catch24: true,
+        ..Catch22Options::default()
+    };
+
+    let mut workspace = Workspace::new(numbers.len()).unwrap();
+    for len in 1..=numbers.len() {
+        let values = &numbers[..len];
+        let expected = catch22_all_with(values, options).map(|o| o.values);
+        let allocations = count_allocations(|| {
+            let _ = workspace.extract(values, options);
+        });
+        assert_eq!(allocations, 0, "{len} samples");
+
+        let actual = workspace.extract(values, options).map(<[f64]>::to_vec);
+        match (actual, expected) {
+            (Ok(actual), Ok(expected)) => {
+                for (a, e) in actual.iter().zip(expected.iter()) {
+                    assert!(a == e || (a.is_nan() && e.is_nan()));
+                }
+            }
+            (actual, expected) => assert_eq!(actual.is_err(), expected.is_err()),
+        }
+    }
+}